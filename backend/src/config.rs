@@ -0,0 +1,211 @@
+use std::{
+    fs,
+    net::{IpAddr, Ipv4Addr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use log::LevelFilter;
+use serde::Deserialize;
+use shared::models::{game_settings::GameSettings, PORT};
+
+// read when no config file is given - it is fine if it does not exist
+const DEFAULT_CONFIG_PATH: &str = "backend.toml";
+
+// more players do not fit onto the board in a sensible way
+const MAX_PLAYERS_PER_LOBBY: usize = 16;
+
+pub const USAGE: &str = "Usage: backend [--config <file>] [--bind <address>] [--port <port>]
+               [--max-players <count>] [--log-level <off|error|warn|info|debug|trace>]
+               [--tick-rate <ticks>] [--board-width <width>] [--board-height <height>]
+               [--move-speed <speed>] [--rotation-speed <speed>] [--collision-radius <radius>]
+               [--unspawnable-edge <distance>]";
+
+/// Everything the backend can be configured with - flags overrule the config file, which overrules the defaults
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub max_players_per_lobby: usize, // bots count as players, spectators do not
+    pub log_level: LevelFilter,
+    pub game: GameSettings, // every new lobby starts with these settings - the host can change them
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: PORT.parse().unwrap_or(11255),
+            max_players_per_lobby: 8,
+            log_level: LevelFilter::Info,
+            game: GameSettings::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file and applies the flags on top of it - the error tells what is wrong with them
+    pub fn load(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let flags = parse_flags(args)?;
+
+        // the config file is the only flag that has to be known before the others can be applied
+        let config_path = flags
+            .iter()
+            .find(|(flag, _)| flag == "--config")
+            .map(|(_, value)| PathBuf::from(value));
+        let mut config = match config_path {
+            Some(path) => Self::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Config::default(),
+        };
+
+        for (flag, value) in flags.iter() {
+            config.apply_flag(flag, value)?;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read config file {}: {}", path.display(), err))?;
+        toml::from_str(&content)
+            .map_err(|err| format!("Invalid config file {}: {}", path.display(), err))
+    }
+
+    fn apply_flag(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        match flag {
+            "--config" => {}
+            "--bind" => self.bind_address = parse_value(flag, value)?,
+            "--port" => self.port = parse_value(flag, value)?,
+            "--max-players" => self.max_players_per_lobby = parse_value(flag, value)?,
+            "--log-level" => self.log_level = parse_value(flag, value)?,
+            "--tick-rate" => self.game.ticks_per_second = parse_value(flag, value)?,
+            "--board-width" => self.game.board_width = parse_value(flag, value)?,
+            "--board-height" => self.game.board_height = parse_value(flag, value)?,
+            "--move-speed" => self.game.move_speed = parse_value(flag, value)?,
+            "--rotation-speed" => self.game.rotation_speed = parse_value(flag, value)?,
+            "--collision-radius" => self.game.collision_radius = parse_value(flag, value)?,
+            "--unspawnable-edge" => self.game.unspawnable_edge = parse_value(flag, value)?,
+            _ => return Err(format!("Unknown flag {}\n{}", flag, USAGE)),
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.port == 0 {
+            return Err("Port has to be between 1 and 65535".to_string());
+        }
+        if !(2..=MAX_PLAYERS_PER_LOBBY).contains(&self.max_players_per_lobby) {
+            return Err(format!(
+                "Max players per lobby has to be between 2 and {} (was {})",
+                MAX_PLAYERS_PER_LOBBY, self.max_players_per_lobby
+            ));
+        }
+        self.game
+            .validate()
+            .map_err(|err| format!("Invalid game settings: {}", err))
+    }
+}
+
+/// Whether the usage should be shown instead of starting the backend
+pub fn is_help_requested(args: &[String]) -> bool {
+    // every flag is followed by its value, so only every second argument can be a flag
+    args.iter().step_by(2).any(|arg| arg == "--help")
+}
+
+// every flag needs a value - --help is handled before the flags are parsed
+fn parse_flags(mut args: impl Iterator<Item = String>) -> Result<Vec<(String, String)>, String> {
+    let mut flags = Vec::new();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{} needs a value\n{}", flag, USAGE))?;
+        flags.push((flag, value));
+    }
+    Ok(flags)
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    // every test gets its own file, so they can run at the same time
+    fn config_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("backend-config-test-{}.toml", name));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn flags_overrule_the_config_file() {
+        let path = config_file(
+            "precedence",
+            "port = 4000\nmax_players_per_lobby = 4\n[game]\nmove_speed = 150.0\nrotation_speed = 3.0\n",
+        );
+        let config = Config::load(args(&[
+            "--config",
+            &path,
+            "--port",
+            "5000",
+            "--move-speed",
+            "100",
+        ]))
+        .unwrap();
+
+        assert_eq!(config.port, 5000);
+        assert_eq!(config.game.move_speed, 100.0);
+        // values without a flag are taken from the file
+        assert_eq!(config.max_players_per_lobby, 4);
+        assert_eq!(config.game.rotation_speed, 3.0);
+        // values neither in the file nor given as flag keep their default
+        assert_eq!(config.game.board_width, GameSettings::default().board_width);
+    }
+
+    #[test]
+    fn unknown_values_in_the_config_file_are_rejected() {
+        let path = config_file("unknown-field", "prot = 4000\n");
+        assert!(Config::load(args(&["--config", &path])).is_err());
+
+        let path = config_file("unknown-game-field", "[game]\nmove_sped = 100.0\n");
+        assert!(Config::load(args(&["--config", &path])).is_err());
+    }
+
+    #[test]
+    fn invalid_flags_are_rejected() {
+        assert!(Config::load(args(&["--prot", "4000"])).is_err());
+        assert!(Config::load(args(&["--port", "not-a-port"])).is_err());
+        assert!(Config::load(args(&["--port", "0"])).is_err());
+        assert!(Config::load(args(&["--max-players", "1"])).is_err());
+        assert!(Config::load(args(&["--tick-rate", "0"])).is_err());
+        assert!(Config::load(args(&["--port"])).is_err());
+    }
+
+    #[test]
+    fn help_is_only_found_where_a_flag_is_expected() {
+        let help = |args: &[&str]| {
+            is_help_requested(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+        };
+
+        assert!(help(&["--help"]));
+        assert!(help(&["--port", "5000", "--help"]));
+        assert!(!help(&["--config", "--help"]));
+    }
+}
@@ -1,227 +1,833 @@
-use std::{collections::HashMap, f32::consts::PI, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use async_std::{net::TcpStream, sync::Mutex};
-use async_tungstenite::{tungstenite::Message, WebSocketStream};
-use futures::{stream::SplitSink, SinkExt};
+use async_std::sync::Mutex;
 use rand::Rng;
 use shared::models::{
+    bot::Bot,
     direction::Direction,
-    network_message::{ConnectionInfoMessage, GameStateMessage, NetworkMessage},
+    display_name::unique_display_name,
+    game_match::{GameMatch, ROUNDS_PER_MATCH},
+    game_settings::GameSettings,
+    network_message::{
+        BotDifficulty, ConnectionInfoMessage, GameStateMessage, LobbyMember, LobbyStatusMessage,
+        NetworkMessage, TrailUpdate,
+    },
+    player_color::PlayerColor,
     player_states::{LobbyState, PlayerStates},
-    GAME_BOARD_HEIGHT, GAME_BOARD_WIDTH, MILLIS_PER_TICK, UNSPAWNABLE_EDGE,
+    replay::{Replay, ReplayChunkMessage},
+    simulation::PlayerInput,
+    simulation_rng::SimulationRng,
+};
+use tokio::{
+    task::JoinHandle,
+    time::{interval, Instant, Interval, MissedTickBehavior},
 };
-use tokio::time::interval;
+
 use uuid::Uuid;
 
+use crate::{
+    outgoing_networking::{send_message, WriteSocket},
+    replay_recorder::{save_replay, ReplayRecorder},
+};
+
+// how long we remember the trail lengths - players acknowledging older game-states get a keyframe
+const TRAIL_HISTORY_SECONDS: f32 = 2.0;
+
+// if the server falls behind, at most this many ticks are computed at once to catch up again
+const MAX_CATCH_UP_TICKS: u32 = 5;
+
+/// Connection of a player inside the lobby
+pub struct PlayerConnection {
+    pub write_socket: WriteSocket,
+    pub acked_tick: Option<u64>, // last game-state the player has applied - none means he needs a keyframe
+    pub resume_token: String,    // lets the player take his slot back after losing the connection
+}
+
 /// GameState that handles all the state in the game
 pub struct GameState {
-    pub players: Vec<PlayerStates>,
-    pub lobby_state: LobbyState,
-    pub player_write_sockets: HashMap<String, SplitSink<WebSocketStream<TcpStream>, Message>>,
+    pub lobby_id: String,
+    pub game_match: GameMatch, // the rules of the match - players, power-ups, rounds and scores
+    pub player_connections: HashMap<String, PlayerConnection>,
+    pub host_id: Option<String>, // the first player inside the lobby - passed on when he leaves
+    max_players: usize,          // bots take a place as well, spectators do not
+    spectators: HashMap<String, String>, // names of the connections that only watch - they have no player
+    bots: Vec<(String, Bot)>, // players that are steered by the server, in the order of the players - they have no connection
+    bot_rng: SimulationRng, // kept apart from the simulation, so the bots do not change its random numbers
+    ready_players: HashSet<String>,
+    trail_length_history: VecDeque<(u64, HashMap<String, usize>)>, // trail lengths of every player at the last ticks
+    replay_recorder: Option<ReplayRecorder>, // only recording while a match is played
+    is_closed: bool, // the lobby was removed from the registry - nobody can enter it anymore
 }
 
 impl GameState {
+    /// Creates a clean game-state for the lobby with the given id
+    pub fn new(lobby_id: &str, settings: GameSettings, max_players: usize) -> Self {
+        let mut game_match = GameMatch::new();
+        game_match.simulation.settings = settings;
+
+        GameState {
+            lobby_id: lobby_id.to_string(),
+            game_match,
+            player_connections: HashMap::new(),
+            host_id: None,
+            max_players,
+            spectators: HashMap::new(),
+            bots: Vec::new(),
+            bot_rng: SimulationRng::default(),
+            ready_players: HashSet::new(),
+            trail_length_history: VecDeque::new(),
+            replay_recorder: None,
+            is_closed: false,
+        }
+    }
+
     pub fn connecting_player(
         &mut self,
-        write_socket: SplitSink<WebSocketStream<TcpStream>, Message>,
-    ) -> String {
-        let uuid = Uuid::new_v4().to_string();
-        let new_player = PlayerStates::new(&uuid);
-        self.players.push(new_player);
+        uuid: &str,
+        display_name: &str,
+        preferred_color: Option<PlayerColor>,
+        write_socket: WriteSocket,
+        resume_token: &str,
+    ) -> Result<(), String> {
+        self.ensure_lobby_is_not_full()?;
+
+        let display_name = self.unique_display_name(uuid, display_name);
+        let mut new_player = PlayerStates::new(uuid, &display_name);
+        new_player.color = self.free_color(uuid, preferred_color);
+        // players joining into a running round have to wait for the next one
+        new_player.is_alive = !self.game_match.is_running();
+        self.game_match.simulation.players.push(new_player);
+
+        if self.host_id.is_none() {
+            self.host_id = Some(uuid.to_string());
+        }
+
+        self.player_connections.insert(
+            uuid.to_string(),
+            PlayerConnection {
+                write_socket,
+                acked_tick: None,
+                resume_token: resume_token.to_string(),
+            },
+        );
+        Ok(())
+    }
+
+    /// The spectator receives every game-state, but is never spawned into a round
+    pub fn connecting_spectator(
+        &mut self,
+        uuid: &str,
+        display_name: &str,
+        write_socket: WriteSocket,
+        resume_token: &str,
+    ) {
+        self.spectators
+            .insert(uuid.to_string(), display_name.to_string());
+
+        self.player_connections.insert(
+            uuid.to_string(),
+            PlayerConnection {
+                write_socket,
+                acked_tick: None,
+                resume_token: resume_token.to_string(),
+            },
+        );
+    }
+
+    /// Adds a player that is steered by the server - only the host can do it before the match starts
+    pub fn add_bot(&mut self, uuid: &str, difficulty: BotDifficulty) -> Result<(), String> {
+        self.ensure_no_match_is_running()?;
+        if self.host_id.as_deref() != Some(uuid) {
+            return Err("Only the host can add bots".to_string());
+        }
+        self.ensure_lobby_is_not_full()?;
+
+        let bot = Bot::new(difficulty);
+        let bot_id = Uuid::new_v4().to_string();
+        let display_name = self.unique_display_name(&bot_id, bot.name());
+        let mut bot_player = PlayerStates::new(&bot_id, &display_name);
+        bot_player.color = self.free_color(&bot_id, None);
+        self.game_match.simulation.players.push(bot_player);
+        self.bots.push((bot_id, bot));
+
+        Ok(())
+    }
+
+    /// Changes the rules of the next match - only the host can do it before the match starts
+    pub fn change_settings(&mut self, uuid: &str, settings: GameSettings) -> Result<(), String> {
+        self.ensure_no_match_is_running()?;
+        if self.host_id.as_deref() != Some(uuid) {
+            return Err("Only the host can change the settings".to_string());
+        }
+        settings.validate()?;
 
-        self.player_write_sockets.insert(uuid.clone(), write_socket);
+        self.game_match.simulation.settings = settings;
+        Ok(())
+    }
+
+    pub fn settings(&self) -> &GameSettings {
+        &self.game_match.simulation.settings
+    }
 
-        uuid
+    /// The player lost his connection - he keeps his slot but nothing is sent to him anymore
+    pub fn suspend_player(&mut self, uuid: &str) {
+        self.player_connections.remove(uuid);
     }
 
-    pub fn disconnecting_player(&mut self, uuid: &str) -> () {
-        let remove_index = self
+    /// The player is back with a new connection - he gets a keyframe with the next game-state
+    pub fn resume_player(&mut self, uuid: &str, write_socket: WriteSocket, resume_token: &str) {
+        self.player_connections.insert(
+            uuid.to_string(),
+            PlayerConnection {
+                write_socket,
+                acked_tick: None,
+                resume_token: resume_token.to_string(),
+            },
+        );
+    }
+
+    pub fn disconnecting_player(&mut self, uuid: &str) {
+        // spectators have nothing to clean up besides their connection
+        if self.spectators.remove(uuid).is_some() {
+            self.player_connections.remove(uuid);
+            return;
+        }
+
+        // an expired session might belong to a player that was removed already
+        let Some(remove_index) = self
+            .game_match
+            .simulation
             .players
             .iter()
             .position(|p| p.id == uuid)
-            .expect("Could not find player!");
+        else {
+            self.player_connections.remove(uuid);
+            return;
+        };
+
+        self.game_match.simulation.players.remove(remove_index);
+        self.game_match.scores.remove(uuid);
+        self.ready_players.remove(uuid);
+
+        // the player that is inside the lobby the longest becomes the new host - bots can not be hosts
+        if self.host_id.as_deref() == Some(uuid) {
+            self.host_id = self
+                .game_match
+                .simulation
+                .players
+                .iter()
+                .find(|p| !self.is_bot(&p.id))
+                .map(|p| p.id.clone());
+        }
+
+        // the players that are left might all be ready now
+        self.start_match_if_everyone_is_ready();
+
+        // remove connection
+        self.player_connections.remove(uuid);
+    }
+
+    /// Gives the player a new name - it is made unique inside of the lobby
+    pub fn rename_player(&mut self, uuid: &str, display_name: &str) {
+        let display_name = self.unique_display_name(uuid, display_name);
+        if let Some(player) = self
+            .game_match
+            .simulation
+            .players
+            .iter_mut()
+            .find(|p| p.id == uuid)
+        {
+            player.display_name = display_name;
+        } else if let Some(spectator_name) = self.spectators.get_mut(uuid) {
+            *spectator_name = display_name;
+        }
+    }
+
+    /// Gives the player the color he prefers - if another player already has it, he gets a free one
+    pub fn recolor_player(&mut self, uuid: &str, preferred_color: Option<PlayerColor>) {
+        let color = self.free_color(uuid, preferred_color);
+        if let Some(player) = self
+            .game_match
+            .simulation
+            .players
+            .iter_mut()
+            .find(|p| p.id == uuid)
+        {
+            player.color = color;
+        }
+    }
 
-        self.players.remove(remove_index);
+    // colors of all other players in the lobby are taken
+    fn free_color(&self, uuid: &str, preferred_color: Option<PlayerColor>) -> PlayerColor {
+        let taken_colors: Vec<PlayerColor> = self
+            .game_match
+            .simulation
+            .players
+            .iter()
+            .filter(|p| p.id != uuid)
+            .map(|p| p.color)
+            .collect();
 
-        // remove socket
-        self.player_write_sockets.remove(uuid);
+        PlayerColor::pick_free(preferred_color, &taken_colors)
     }
 
-    pub fn init_game(&mut self) -> () {
-        // Initialize random generator
-        let mut rng = rand::rng();
+    // names of all other players in the lobby are taken
+    fn unique_display_name(&self, uuid: &str, display_name: &str) -> String {
+        let taken_names: Vec<&str> = self
+            .game_match
+            .simulation
+            .players
+            .iter()
+            .filter(|p| p.id != uuid)
+            .map(|p| p.display_name.as_str())
+            .collect();
 
-        // Set bounds for random position
-        let x_max = (GAME_BOARD_WIDTH / 2.0) - UNSPAWNABLE_EDGE;
-        let x_min = x_max * -1.0;
-        let y_max = (GAME_BOARD_HEIGHT / 2.0) - UNSPAWNABLE_EDGE;
-        let y_min = y_max * -1.0;
+        unique_display_name(display_name, &taken_names)
+    }
 
-        // go over all players and give them random position and directions
-        for player in self.players.iter_mut() {
-            // random position in defined bounds
-            player.position_x = rng.random_range(x_min..x_max);
-            player.position_y = rng.random_range(y_min..y_max);
+    /// Nobody is inside the lobby anymore - neither playing nor watching. Bots do not keep the lobby open.
+    pub fn close(&mut self) {
+        self.is_closed = true;
+    }
 
-            // give player a random direction
-            player.direction = rng.random_range(0.0..(2.0 * PI));
+    pub fn is_closed(&self) -> bool {
+        self.is_closed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.game_match
+            .simulation
+            .players
+            .iter()
+            .all(|p| self.is_bot(&p.id))
+            && self.spectators.is_empty()
+    }
 
-            // reset player state
-            player.is_alive = true;
-            player.current_direction = Direction::Straight;
-            player.trail = Vec::new();
+    /// Only the host can start the match without everyone being ready
+    pub fn request_start(&mut self, uuid: &str) -> Result<(), String> {
+        self.ensure_no_match_is_running()?;
+        if self.host_id.as_deref() != Some(uuid) {
+            return Err("Only the host can start the match".to_string());
         }
+
+        self.start_match();
+        Ok(())
     }
 
-    pub fn next_step(&mut self) -> () {
-        // move all players - calculate directions
-        for player in self.players.as_mut_slice() {
-            if player.is_alive {
-                player.steer_player();
-                player.move_player();
-            }
+    /// The match starts as soon as every player inside the lobby is ready
+    pub fn toggle_ready(&mut self, uuid: &str) -> Result<(), String> {
+        self.ensure_no_match_is_running()?;
+        if self.spectators.contains_key(uuid) {
+            return Err("Spectators can not get ready".to_string());
         }
 
-        //check for collisions
-        self.check_collision();
+        if !self.ready_players.remove(uuid) {
+            self.ready_players.insert(uuid.to_string());
+        }
+        self.start_match_if_everyone_is_ready();
+        Ok(())
+    }
 
-        // check if finished (is finished when only 1 or none player are alive)
-        let alive_players_count = self.players.iter().filter(|p| p.is_alive).count();
-        if alive_players_count <= 1 {
-            self.lobby_state = LobbyState::Finished;
+    fn ensure_no_match_is_running(&self) -> Result<(), String> {
+        if self.game_match.is_running() {
+            return Err("A match is already running".to_string());
         }
+        Ok(())
     }
 
-    pub fn check_collision(&mut self) -> () {
-        for i in 0..self.players.len() {
-            let (first_part, second_part) = self.players.split_at_mut(i + 1);
-            let first_player = &mut first_part[i];
+    fn ensure_lobby_is_not_full(&self) -> Result<(), String> {
+        if self.game_match.simulation.players.len() >= self.max_players {
+            return Err(format!("The lobby is full ({} players)", self.max_players));
+        }
+        Ok(())
+    }
 
-            if !first_player.is_alive {
-                continue;
-            }
+    fn start_match_if_everyone_is_ready(&mut self) {
+        // bots are always ready - but they can not start a match on their own
+        let mut humans = self
+            .game_match
+            .simulation
+            .players
+            .iter()
+            .filter(|p| !self.is_bot(&p.id));
+        let everyone_is_ready =
+            humans.clone().next().is_some() && humans.all(|p| self.ready_players.contains(&p.id));
 
-            // check if we collide with our own trail (leave out the newest 5 trail segments or we would always collide with ourself)
-            if first_player.trail.len() > 5
-                && first_player.collide_with_trail_collection(
-                    &first_player.trail[0..first_player.trail.len() - 5].to_vec(),
-                )
-            {
-                first_player.is_alive = false;
-                continue;
-            }
+        if everyone_is_ready && self.ensure_no_match_is_running().is_ok() {
+            self.start_match();
+        }
+    }
 
-            // check if we are out of bounds
-            if first_player.position_x > (GAME_BOARD_WIDTH / 2.0)
-                || first_player.position_x < (GAME_BOARD_WIDTH / 2.0 * -1.0)
-                || first_player.position_y > (GAME_BOARD_HEIGHT / 2.0)
-                || first_player.position_y < (GAME_BOARD_HEIGHT / 2.0 * -1.0)
-            {
-                first_player.is_alive = false;
+    /// Starts a new match - all scores are reset and the countdown of the first round begins
+    fn start_match(&mut self) {
+        // everyone has to get ready again for the next match
+        self.ready_players.clear();
+
+        // every match gets its own seed - the bots draw their random numbers apart from the simulation
+        let seed = rand::rng().random();
+        self.game_match.start(seed);
+        self.bot_rng = SimulationRng::new(seed);
+        self.replay_recorder = Some(ReplayRecorder::new(
+            &self.lobby_id,
+            *self.settings(),
+            server_time_millis(),
+        ));
+        self.round_started();
+    }
+
+    // all remembered trail lengths are outdated now - everyone will get a keyframe
+    fn round_started(&mut self) {
+        self.trail_length_history.clear();
+        if let Some(replay_recorder) = self.replay_recorder.as_mut() {
+            replay_recorder.start_keyframe();
+        }
+    }
+
+    /// Moves the lobby one tick further - counts down, runs the game or waits for the next round
+    pub fn advance_lobby_state(&mut self) {
+        let inputs = if self.game_match.lobby_state == LobbyState::Running {
+            // bots send their inputs the same way the players do
+            self.steer_bots();
+            self.next_inputs()
+        } else {
+            Vec::new()
+        };
+
+        let round_before = self.game_match.round;
+        let trail_lengths_before = self.current_trail_lengths();
+
+        self.game_match.advance(&inputs);
+
+        if self.game_match.round != round_before {
+            self.round_started();
+            return;
+        }
+
+        // the trails got shorter (they were erased) - everyone needs a keyframe, the replay as well
+        let trails_got_shorter = self.game_match.simulation.players.iter().any(|p| {
+            trail_lengths_before
+                .get(&p.id)
+                .is_some_and(|&length| p.trail.len() < length)
+        });
+        if trails_got_shorter {
+            self.trail_length_history.clear();
+            if let Some(replay_recorder) = self.replay_recorder.as_mut() {
+                replay_recorder.start_keyframe();
             }
+        }
 
-            for second_player in second_part {
-                if second_player.is_alive
-                    && second_player.collide_with_trail_collection(&first_player.trail)
-                {
-                    second_player.is_alive = false;
-                }
-                first_player.collides_with_other_player(second_player);
+        if self.game_match.lobby_state == LobbyState::Finished {
+            log::info!(
+                "Lobby {} finished at tick {}",
+                self.lobby_id,
+                self.game_match.tick
+            );
+        }
+    }
+
+    // every player applies one of his buffered inputs per tick
+    fn next_inputs(&mut self) -> Vec<PlayerInput> {
+        self.game_match
+            .simulation
+            .players
+            .iter_mut()
+            .filter(|p| p.is_alive)
+            .filter_map(|p| {
+                p.next_input().map(|(sequence, direction)| PlayerInput {
+                    player_id: p.id.clone(),
+                    sequence,
+                    direction,
+                })
+            })
+            .collect()
+    }
+
+    fn is_bot(&self, uuid: &str) -> bool {
+        self.bots.iter().any(|(bot_id, _)| bot_id == uuid)
+    }
+
+    // the bots share their random numbers - they always draw them in the same order
+    fn steer_bots(&mut self) {
+        let players = &self.game_match.simulation.players;
+        let settings = &self.game_match.simulation.settings;
+        let mut inputs = Vec::new();
+        for (bot_id, bot) in self.bots.iter_mut() {
+            if let Some(player) = players.iter().find(|p| &p.id == bot_id && p.is_alive) {
+                let (sequence, direction) =
+                    bot.next_input(player, players, settings, &mut self.bot_rng);
+                inputs.push((bot_id.clone(), sequence, direction));
             }
         }
+
+        for (bot_id, sequence, direction) in inputs {
+            self.update_player(&bot_id, sequence, direction);
+        }
     }
 
     pub async fn notify_about_player_joining(&mut self) -> () {
-        let player_count = self.player_write_sockets.len();
+        let player_count = self.player_connections.len();
+
+        for (uuid, connection) in &self.player_connections {
+            let spectator_name = self.spectators.get(uuid);
+            let (display_name, color) = self
+                .game_match
+                .simulation
+                .players
+                .iter()
+                .find(|p| &p.id == uuid)
+                .map(|p| (p.display_name.clone(), p.color))
+                .or_else(|| spectator_name.map(|name| (name.clone(), PlayerColor::default())))
+                .unwrap_or_default();
 
-        for (uuid, write_socket) in &mut self.player_write_sockets {
             let message = NetworkMessage::ConnectionInfo(ConnectionInfoMessage {
                 player_id: uuid.to_string(),
                 players_connected: player_count as u32,
+                lobby_id: Some(self.lobby_id.clone()),
+                display_name,
+                color,
+                resume_token: connection.resume_token.clone(),
+                is_spectator: spectator_name.is_some(),
             });
 
-            match serde_json::to_string(&message) {
-                Ok(serialized_player) => {
-                    if let Err(e) = write_socket.send(Message::Text(serialized_player)).await {
-                        println!("Failed to send message to {}: {:?}", uuid, e);
-                    }
-                },
-                Err(_) => println!("Failed to serialize Player!"),
-            }
+            send_message(&connection.write_socket, uuid, &message).await;
+        }
+
+        self.notify_about_lobby_status().await;
+    }
 
+    /// Tells every player inside the lobby who the host is, who is ready and who is watching
+    pub async fn notify_about_lobby_status(&self) {
+        let members = self
+            .game_match
+            .simulation
+            .players
+            .iter()
+            .map(|p| LobbyMember {
+                player_id: p.id.clone(),
+                display_name: p.display_name.clone(),
+                color: p.color,
+                is_ready: self.ready_players.contains(&p.id) || self.is_bot(&p.id),
+                is_connected: self.player_connections.contains_key(&p.id) || self.is_bot(&p.id),
+                is_bot: self.is_bot(&p.id),
+            })
+            .collect();
+
+        let message = NetworkMessage::LobbyStatus(LobbyStatusMessage {
+            host_id: self.host_id.clone(),
+            members,
+            spectators: self.spectators.values().cloned().collect(),
+            settings: *self.settings(),
+        });
+
+        for (uuid, connection) in &self.player_connections {
+            send_message(&connection.write_socket, uuid, &message).await;
         }
     }
 
-    pub async fn notify_all_players(&mut self, message: String) -> () {
-        for (uuid, write_socket) in &mut self.player_write_sockets {
-            if let Err(e) = write_socket.send(Message::Text(message.clone())).await {
-                println!("Failed to send message to {}: {:?}", uuid, e);
-            }
+    /// Adds the current tick to the replay - returns the whole replay once the match is finished
+    pub fn record_replay_frame(&mut self) -> Option<Replay> {
+        let mut replay_recorder = self.replay_recorder.take()?;
+
+        if replay_recorder.wants_frame(&self.game_match.lobby_state) {
+            let frame = self.get_game_state(replay_recorder.trail_lengths(), server_time_millis());
+            replay_recorder.push_frame(frame, self.current_trail_lengths());
+        }
+
+        if self.game_match.lobby_state == LobbyState::Finished {
+            return Some(replay_recorder.finish());
         }
+        self.replay_recorder = Some(replay_recorder);
+        None
     }
 
-    pub fn get_game_state_message(&self) -> NetworkMessage {
-        let lobby_state = match self.lobby_state {
-            LobbyState::Waiting => LobbyState::Waiting,
-            LobbyState::Countdown(countdown) => LobbyState::Countdown(countdown),
-            LobbyState::Running => LobbyState::Running,
-            LobbyState::Finished => LobbyState::Finished,
-        };
+    /// Everyone inside the lobby when the match finished - they get its recording
+    pub fn replay_receivers(&self) -> Vec<(String, WriteSocket)> {
+        self.player_connections
+            .iter()
+            .map(|(uuid, connection)| (uuid.clone(), connection.write_socket.clone()))
+            .collect()
+    }
+
+    /// Sends every player the game-state of the current tick - only containing the trail points he has not acknowledged yet
+    pub async fn notify_all_players_about_game_state(&mut self) {
+        self.remember_trail_lengths();
+
+        let server_time_millis = server_time_millis();
 
-        let player_states: Vec<PlayerStates> = self.players.clone();
+        for (uuid, connection) in &self.player_connections {
+            let acked_trail_lengths = connection.acked_tick.and_then(|acked_tick| {
+                self.trail_length_history
+                    .iter()
+                    .find(|(tick, _)| *tick == acked_tick)
+                    .map(|(_, trail_lengths)| trail_lengths)
+            });
+
+            let message = self.get_game_state_message(acked_trail_lengths, server_time_millis);
+            send_message(&connection.write_socket, uuid, &message).await;
+        }
+    }
+
+    fn current_trail_lengths(&self) -> HashMap<String, usize> {
+        self.game_match
+            .simulation
+            .players
+            .iter()
+            .map(|p| (p.id.clone(), p.trail.len()))
+            .collect()
+    }
+
+    fn remember_trail_lengths(&mut self) {
+        let trail_lengths = self.current_trail_lengths();
+
+        self.trail_length_history
+            .push_back((self.game_match.tick, trail_lengths));
+        if self.trail_length_history.len() > self.settings().ticks(TRAIL_HISTORY_SECONDS) as usize {
+            self.trail_length_history.pop_front();
+        }
+    }
 
-        NetworkMessage::GameState(GameStateMessage {
-            lobby_state: lobby_state,
-            player_states: player_states,
-        })
+    pub fn get_game_state_message(
+        &self,
+        acked_trail_lengths: Option<&HashMap<String, usize>>,
+        server_time_millis: u64,
+    ) -> NetworkMessage {
+        NetworkMessage::GameState(self.get_game_state(acked_trail_lengths, server_time_millis))
     }
 
-    pub fn update_player(&mut self, player_id: &str, direction: Direction) -> () {
-        let player_index = self
+    /// Builds the game-state - if no acknowledged trail lengths are given, it will be a keyframe
+    fn get_game_state(
+        &self,
+        acked_trail_lengths: Option<&HashMap<String, usize>>,
+        server_time_millis: u64,
+    ) -> GameStateMessage {
+        let player_states: Vec<PlayerStates> = self
+            .game_match
+            .simulation
+            .players
+            .iter()
+            .map(|p| p.without_trail())
+            .collect();
+
+        let trail_updates = self
+            .game_match
+            .simulation
             .players
             .iter()
-            .position(|p| p.id == player_id)
-            .expect("Could not find player!");
+            .map(|p| {
+                // a player that was not known at the acknowledged tick gets his whole trail
+                let start_index = acked_trail_lengths
+                    .and_then(|trail_lengths| trail_lengths.get(&p.id))
+                    .map_or(0, |&length| length.min(p.trail.len()));
+
+                TrailUpdate {
+                    player_id: p.id.clone(),
+                    start_index: start_index as u32,
+                    points: p.trail[start_index..].to_vec(),
+                }
+            })
+            .collect();
+
+        GameStateMessage {
+            tick: self.game_match.tick,
+            server_time_millis,
+            is_keyframe: acked_trail_lengths.is_none(),
+            lobby_state: self.game_match.lobby_state.clone(),
+            player_states,
+            trail_updates,
+            power_ups: self.game_match.simulation.power_ups.clone(),
+            round: self.game_match.round,
+            rounds_per_match: ROUNDS_PER_MATCH,
+            scores: self.game_match.scores(),
+            round_results: match self.game_match.lobby_state {
+                LobbyState::RoundOver(_) | LobbyState::Finished => {
+                    Some(self.game_match.round_results())
+                }
+                _ => None,
+            },
+            simulation: self.game_match.simulation.sync(),
+        }
+    }
+
+    /// The player has applied the game-state of the given tick
+    pub fn acknowledge_game_state(&mut self, player_id: &str, tick: u64) {
+        if let Some(connection) = self.player_connections.get_mut(player_id) {
+            if connection
+                .acked_tick
+                .is_none_or(|acked_tick| acked_tick < tick)
+            {
+                connection.acked_tick = Some(tick);
+            }
+        }
+    }
+
+    /// The player will get a keyframe with the next game-state
+    pub fn request_resync(&mut self, player_id: &str) {
+        if let Some(connection) = self.player_connections.get_mut(player_id) {
+            connection.acked_tick = None;
+        }
+    }
+
+    pub fn update_player(&mut self, player_id: &str, sequence: u32, direction: Direction) {
+        // spectators have no player that could be steered
+        let Some(player) = self
+            .game_match
+            .simulation
+            .players
+            .iter_mut()
+            .find(|p| p.id == player_id)
+        else {
+            return;
+        };
+
+        // buffer the input, the direction is updated with the next ticks
+        player.queue_input(sequence, direction);
+    }
+}
+
+/// Millis since unix-epoch - the clients use it for ordering the game-states
+fn server_time_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64)
+}
+
+/// Keeps track of how long the ticks of a lobby really take and reports the ones that took longer than planned
+struct TickStatistics {
+    measured_ticks: u32,
+    overruns: u32,
+    slowest_tick: Duration,
+}
+
+impl TickStatistics {
+    fn new() -> Self {
+        TickStatistics {
+            measured_ticks: 0,
+            overruns: 0,
+            slowest_tick: Duration::ZERO,
+        }
+    }
+
+    fn measure(&mut self, lobby_id: &str, tick_duration: Duration, budget: Duration) {
+        self.measured_ticks += 1;
+        self.slowest_tick = self.slowest_tick.max(tick_duration);
+        if tick_duration > budget {
+            self.overruns += 1;
+        }
+
+        // report once per second at most, so an overloaded server does not also drown in logs
+        if self.measured_ticks as f32 * budget.as_secs_f32() >= 1.0 {
+            if self.overruns > 0 {
+                log::warn!(
+                    "Lobby {}: {} of the last {} ticks took longer than {:?} (slowest took {:?})",
+                    lobby_id,
+                    self.overruns,
+                    self.measured_ticks,
+                    budget,
+                    self.slowest_tick
+                );
+            }
+            *self = TickStatistics::new();
+        }
+    }
+}
 
-        // update player direction
-        if let Some(player) = self.players.get_mut(player_index) {
-            player.set_direction(direction);
-        } 
+fn tick_interval(tick_budget: Duration) -> Interval {
+    let mut interval = interval(tick_budget);
+    // late interval-ticks are not fired in a burst - the accumulator takes care of catching up
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+// the chunks are sent one after another, so the game-states of the next match can get in between
+async fn send_replay(receivers: Vec<(String, WriteSocket)>, chunks: Vec<ReplayChunkMessage>) {
+    for chunk in chunks {
+        let message = NetworkMessage::ReplayChunk(chunk);
+        for (uuid, write_socket) in receivers.iter() {
+            send_message(write_socket, uuid, &message).await;
+        }
     }
 }
 
-pub fn start_up_game_loop(game_state: Arc<Mutex<GameState>>) -> () {
+/// Starts the game-loop of a single lobby - the returned handle can be used to stop it once the lobby is closed.
+/// The elapsed time is accumulated and consumed in fixed ticks, so the simulation keeps its speed even if single ticks are late.
+pub fn start_up_game_loop(game_state: Arc<Mutex<GameState>>) -> JoinHandle<()> {
     tokio::spawn(async move {
-        let mut interval = interval(Duration::from_millis(MILLIS_PER_TICK as u64));
+        let mut tick_budget = game_state.lock().await.settings().tick_duration();
+        let mut interval = tick_interval(tick_budget);
+
+        let mut last_time = Instant::now();
+        let mut accumulator = Duration::ZERO;
+        let mut statistics = TickStatistics::new();
 
         loop {
-            // we just wait for the interval tick down
-            // if computation inside the game-loop is taking to long, we wouldn't be able to produce the wished ticks per second anymore
-            // then we would have to keep track of time elapsed since we started the game-loop computation
             interval.tick().await;
 
+            let now = Instant::now();
+            accumulator += now - last_time;
+            last_time = now;
+
             let mut game_state = game_state.lock().await;
-            let should_update = match game_state.lobby_state {
-                LobbyState::Waiting => false,
-                LobbyState::Countdown(_) => true,
-                LobbyState::Running => true,
-                LobbyState::Finished => false,
-            };
-
-            if should_update {
-                if game_state.lobby_state == LobbyState::Running {
-                    // move to next step in game-state
-                    game_state.next_step();
+
+            // the host changed the tick rate - it can only happen between matches
+            if tick_budget != game_state.settings().tick_duration() {
+                tick_budget = game_state.settings().tick_duration();
+                interval = tick_interval(tick_budget);
+                accumulator = Duration::ZERO;
+            }
+
+            // run as many ticks as the elapsed time requires - but only up to a limit
+            let mut ticks_to_run = 0;
+            while accumulator >= tick_budget && ticks_to_run < MAX_CATCH_UP_TICKS {
+                accumulator -= tick_budget;
+                ticks_to_run += 1;
+            }
+            if accumulator >= tick_budget {
+                let dropped_ticks = accumulator.as_secs_f32() / tick_budget.as_secs_f32();
+                log::warn!(
+                    "Lobby {} is too far behind, dropping {} ticks",
+                    game_state.lobby_id,
+                    dropped_ticks as u32
+                );
+                accumulator = Duration::ZERO;
+            }
+
+            let mut should_notify = false;
+            let mut finished_replay = None;
+            for _ in 0..ticks_to_run {
+                if game_state.game_match.is_running() {
+                    should_notify = true;
+
+                    // count down, move to the next step or start the next round
+                    game_state.advance_lobby_state();
+                    if let Some(replay) = game_state.record_replay_frame() {
+                        finished_replay = Some(replay);
+                    }
                 }
+            }
 
-                // send update to all clients
-                let message = game_state.get_game_state_message();
-                let serialized_message = serde_json::to_string(&message);
-                match serialized_message {
-                    Ok(serialized_message) => game_state.notify_all_players(serialized_message).await,
-                    Err(_) => println!("Failed to serialize Game-State-Message!"),
-                };
+            if should_notify {
+                // send update to all clients - only the newest state after catching up
+                game_state.notify_all_players_about_game_state().await;
             }
+
+            let replay_receivers = finished_replay
+                .as_ref()
+                .map(|_| game_state.replay_receivers());
+
+            let lobby_id = game_state.lobby_id.clone();
+            drop(game_state);
+
+            // sending and writing the replay take a while - the lobby must not wait for it
+            if let (Some(replay), Some(receivers)) = (finished_replay, replay_receivers) {
+                tokio::spawn(send_replay(receivers, replay.chunks()));
+                tokio::task::spawn_blocking(move || save_replay(&replay));
+            }
+
+            statistics.measure(&lobby_id, now.elapsed(), tick_budget);
         }
-    });
+    })
 }
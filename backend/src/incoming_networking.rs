@@ -1,67 +1,127 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use async_std::{
     net::{TcpListener, TcpStream},
     sync::Mutex,
 };
-use async_tungstenite::{accept_async, tungstenite::Message};
+use async_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{
+        handshake::server::{Request, Response},
+        Message,
+    },
+};
 use futures::StreamExt;
-use shared::models::{network_message::NetworkMessage, player_states::LobbyState, PORT};
-use tokio::{task::JoinHandle, time::interval};
+use shared::models::{
+    display_name::{default_display_name, validate_display_name},
+    network_message::{ConnectionInfoMessage, NetworkMessage, RequestRejectedMessage},
+    player_color::PlayerColor,
+    wire_format::{resume_token_from_query, WireFormat},
+};
+use tokio::{task::JoinHandle, time::sleep};
+use uuid::Uuid;
 
-use crate::game_state::GameState;
+use crate::{
+    game_state::GameState,
+    lobby_registry::{
+        get_lobby_list_message, LobbyRegistry, SuspendedSession, RESUME_GRACE_PERIOD,
+    },
+    outgoing_networking::{send_message, ClientSocket, WriteSocket},
+};
+
+/// Everything that belongs to a single websocket-connection
+struct Connection {
+    uuid: String,
+    display_name: String, // name the player asked for - inside a lobby it is made unique
+    preferred_color: Option<PlayerColor>, // inside a lobby he only gets it if nobody else has it
+    resume_token: String, // a new one for every connection - the old one can only be used once
+    write_socket: WriteSocket,
+    lobby: Option<(String, Arc<Mutex<GameState>>)>, // the lobby the player is currently in
+}
 
 /// Opens up a new thread that listens for new connections - for each connection there will be another thread to handle incoming messages
-pub fn spawn_up_listener_thread(game_state: Arc<Mutex<GameState>>) -> JoinHandle<()> {
+pub fn spawn_up_listener_thread(
+    server: TcpListener,
+    lobby_registry: Arc<Mutex<LobbyRegistry>>,
+) -> JoinHandle<()> {
     tokio::spawn(async move {
-        let server = TcpListener::bind(format!("0.0.0.0:{}", PORT))
-            .await
-            .expect("Failed to start TCP-Listener!");
-
         while let Ok((stream, _)) = server.accept().await {
-            // open new thread for each connection and give it the game state
-            let cloned_game_state = Arc::clone(&game_state);
-            tokio::spawn(handle_connection(stream, cloned_game_state));
+            // open new thread for each connection and give it the lobby registry
+            let cloned_lobby_registry = Arc::clone(&lobby_registry);
+            tokio::spawn(handle_connection(stream, cloned_lobby_registry));
         }
     })
 }
 
 /// Handles the websocket-connection, holds it open and listens for incoming messages
-async fn handle_connection(stream: TcpStream, game_state: Arc<Mutex<GameState>>) {
-    let websocket = accept_async(stream)
-        .await
-        .expect("Error during websocket handshake occured!");
+#[allow(clippy::result_large_err)] // the signature of the handshake-callback is given by tungstenite
+async fn handle_connection(stream: TcpStream, lobby_registry: Arc<Mutex<LobbyRegistry>>) {
+    // the player tells us in the query of the url which format he wants to receive and which slot he wants back
+    let mut wire_format = WireFormat::Json;
+    let mut resume_token = None;
+    let websocket = match accept_hdr_async(stream, |request: &Request, response: Response| {
+        wire_format = WireFormat::from_query(request.uri().query());
+        resume_token = resume_token_from_query(request.uri().query());
+        Ok(response)
+    })
+    .await
+    {
+        Ok(websocket) => websocket,
+        Err(err) => {
+            // somebody that does not speak websocket - there is nothing to hold open
+            log::warn!("Error during websocket handshake: {}", err);
+            return;
+        }
+    };
 
     // split the websocket into a read and write stream
     let (write_stream, mut read_stream) = websocket.split();
 
-    let uuid = {
-        let mut game_state = game_state.lock().await;
-        // add the new player to the game state
-        let uuid = game_state.connecting_player(write_stream);
-        // tell the other players about the joined player
-        game_state.notify_about_player_joining().await;
-        uuid
+    let uuid = Uuid::new_v4().to_string();
+    let mut connection = Connection {
+        display_name: default_display_name(&uuid),
+        preferred_color: None,
+        resume_token: Uuid::new_v4().to_string(),
+        uuid,
+        write_socket: Arc::new(Mutex::new(ClientSocket {
+            sink: write_stream,
+            wire_format,
+        })),
+        lobby: None,
     };
 
+    // a player that lost his connection gets his old slot back
+    let is_resumed = match resume_token {
+        Some(resume_token) => resume_session(&lobby_registry, &mut connection, &resume_token).await,
+        None => false,
+    };
+
+    if !is_resumed {
+        // tell the player who he is - he is not part of any lobby yet
+        send_connection_info_without_lobby(&connection).await;
+    }
+
     // handle incoming messages
+    let mut is_closed_by_player = false;
     'messageloop: loop {
         let incoming_message = read_stream.next().await;
 
         if let Some(incoming_message) = incoming_message {
             match incoming_message {
                 Ok(incoming_message) => {
-                    handle_valid_message(incoming_message, &game_state, &uuid).await
+                    // the player closed the connection on purpose - he will not come back
+                    is_closed_by_player |= incoming_message.is_close();
+                    handle_valid_message(incoming_message, &lobby_registry, &mut connection).await
                 }
                 Err(err) => {
                     // log the error - for now just connection closing
                     match err {
                         async_tungstenite::tungstenite::Error::ConnectionClosed => {
-                            println!("Connection closed!");
+                            log::debug!("Connection closed!");
                             break 'messageloop;
                         }
                         _ => {
-                            println!("Unexpected Error occured!");
+                            log::warn!("Unexpected Error occured!");
                         }
                     };
 
@@ -75,65 +135,355 @@ async fn handle_connection(stream: TcpStream, game_state: Arc<Mutex<GameState>>)
         }
     }
 
-    // remove the player from the game state and notify the other players
-    let mut game_state = game_state.lock().await;
-    game_state.disconnecting_player(&uuid);
-    game_state.notify_about_player_joining().await;
-    drop(game_state);
+    if is_closed_by_player {
+        // remove the player from his lobby and notify the other players
+        leave_lobby(&lobby_registry, &mut connection).await;
+    } else {
+        // the connection dropped - the player might come back
+        suspend_session(&lobby_registry, &mut connection).await;
+    }
 }
 
 /// Handles a valid message - deserializes it and acts accordingly
 async fn handle_valid_message(
     message: Message,
-    cloned_game_state: &Arc<Mutex<GameState>>,
-    uuid: &str,
+    lobby_registry: &Arc<Mutex<LobbyRegistry>>,
+    connection: &mut Connection,
 ) -> () {
-    let parsed_message: Result<NetworkMessage, String> = message
-        .to_text()
-        .map_err(|err| format!("{:?}", err)) // convert the error to a string
-        .and_then(|message_text| {
-            serde_json::from_str(message_text)
-                .map_err(|err| format!("Failed deserializing: {}", err))
-        });
+    // incoming messages are decoded depending on their frame - no matter which format the player receives
+    let parsed_message: Result<NetworkMessage, String> = match message {
+        Message::Text(message_text) => NetworkMessage::decode_text(&message_text),
+        Message::Binary(message_bytes) => NetworkMessage::decode_binary(&message_bytes),
+        _ => return, // ping, pong and close frames are handled by tungstenite
+    };
 
     match parsed_message {
         Ok(parsed_message) => {
-            // we only want to listen to request-start and player-update messages
+            // messages that are not related to a game are handled first
             match parsed_message {
-                NetworkMessage::RequestStart(_) => {
-                    // first init the game
-                    let mut game_state = cloned_game_state.lock().await;
-                    game_state.init_game();
-                    drop(game_state);
+                NetworkMessage::ListLobbies(_) => {
+                    let message = get_lobby_list_message(lobby_registry).await;
+                    send_message(&connection.write_socket, &connection.uuid, &message).await;
+                    return;
+                }
+                NetworkMessage::CreateLobby(create_lobby_message) => {
+                    leave_lobby(lobby_registry, connection).await;
 
-                    // then start the countdown
-                    let mut countdown = 5;
-                    let mut interval = interval(Duration::from_secs(1));
-                    interval.tick().await; // first tick will elapse immediately
+                    let (lobby_id, game_state) = lobby_registry
+                        .lock()
+                        .await
+                        .create_lobby(&create_lobby_message.name);
+                    enter_lobby(connection, lobby_id, game_state, false).await;
+                    return;
+                }
+                NetworkMessage::JoinLobby(join_lobby_message) => {
+                    leave_lobby(lobby_registry, connection).await;
 
-                    while countdown > 0 {
-                        // count the start of the game down
-                        //game-loop will send info to the players
-                        let mut game_state = cloned_game_state.lock().await;
-                        game_state.lobby_state = LobbyState::Countdown(countdown);
-                        drop(game_state);
+                    // the registry is not kept locked while the others are notified - a lobby that is closed
+                    // in the meantime is noticed by enter_lobby
+                    let lobby = lobby_registry
+                        .lock()
+                        .await
+                        .get_lobby(&join_lobby_message.lobby_id);
+                    match lobby {
+                        Some(game_state) => {
+                            enter_lobby(
+                                connection,
+                                join_lobby_message.lobby_id,
+                                game_state,
+                                join_lobby_message.as_spectator,
+                            )
+                            .await
+                        }
+                        None => {
+                            log::warn!("Lobby {} does not exist!", join_lobby_message.lobby_id);
+                            send_connection_info_without_lobby(connection).await;
+                        }
+                    }
+                    return;
+                }
+                NetworkMessage::LeaveLobby(_) => {
+                    leave_lobby(lobby_registry, connection).await;
+                    send_connection_info_without_lobby(connection).await;
+                    return;
+                }
+                NetworkMessage::Hello(hello_message) => {
+                    if let Some(display_name) = hello_message.display_name {
+                        match validate_display_name(&display_name) {
+                            Ok(display_name) => connection.display_name = display_name,
+                            Err(err) => {
+                                log::info!("Rejected display name of {}: {}", connection.uuid, err)
+                            }
+                        }
+                    }
+                    if hello_message.preferred_color.is_some() {
+                        connection.preferred_color = hello_message.preferred_color;
+                    }
 
-                        countdown -= 1;
-                        interval.tick().await;
+                    // tell the player which name he got - inside a lobby everyone gets to know it
+                    match &connection.lobby {
+                        Some((_, game_state)) => {
+                            let mut game_state = game_state.lock().await;
+                            game_state.rename_player(&connection.uuid, &connection.display_name);
+                            game_state.recolor_player(&connection.uuid, connection.preferred_color);
+                            game_state.notify_about_player_joining().await;
+                        }
+                        None => send_connection_info_without_lobby(connection).await,
                     }
+                    return;
+                }
+                _ => {}
+            }
+
+            // all other messages are only valid when the player is inside a lobby
+            let Some((_, cloned_game_state)) = &connection.lobby else {
+                log::warn!(
+                    "Received message from {} outside of a lobby!",
+                    connection.uuid
+                );
+                return;
+            };
 
+            // we only want to listen to messages that are related to the game
+            match parsed_message {
+                NetworkMessage::RequestStart(_) => {
+                    // the game-loop counts down and plays all rounds of the match
                     let mut game_state = cloned_game_state.lock().await;
-                    game_state.lobby_state = LobbyState::Running;
-                    // lock will be dropped here automatically
+                    let result = game_state.request_start(&connection.uuid);
+                    drop(game_state);
+
+                    if let Err(reason) = result {
+                        reject_request(connection, reason).await;
+                    }
+                }
+                NetworkMessage::ToggleReady(_) => {
+                    let mut game_state = cloned_game_state.lock().await;
+                    match game_state.toggle_ready(&connection.uuid) {
+                        Ok(()) => game_state.notify_about_lobby_status().await,
+                        Err(reason) => {
+                            drop(game_state);
+                            reject_request(connection, reason).await;
+                        }
+                    }
+                }
+                NetworkMessage::AddBot(add_bot_message) => {
+                    let mut game_state = cloned_game_state.lock().await;
+                    match game_state.add_bot(&connection.uuid, add_bot_message.difficulty) {
+                        Ok(()) => game_state.notify_about_lobby_status().await,
+                        Err(reason) => {
+                            drop(game_state);
+                            reject_request(connection, reason).await;
+                        }
+                    }
+                }
+                NetworkMessage::ChangeSettings(settings) => {
+                    let mut game_state = cloned_game_state.lock().await;
+                    match game_state.change_settings(&connection.uuid, settings) {
+                        Ok(()) => game_state.notify_about_lobby_status().await,
+                        Err(reason) => {
+                            drop(game_state);
+                            reject_request(connection, reason).await;
+                        }
+                    }
                 }
                 NetworkMessage::PlayerUpdate(player_update_message) => {
                     let mut game_state = cloned_game_state.lock().await;
-                    game_state.update_player(&uuid, player_update_message.current_direction);
+                    game_state.update_player(
+                        &connection.uuid,
+                        player_update_message.sequence,
+                        player_update_message.current_direction,
+                    );
                     // lock will be dropped here automatically
                 }
-                _ => println!("Received unexpected message!"),
+                NetworkMessage::StateAck(state_ack_message) => {
+                    let mut game_state = cloned_game_state.lock().await;
+                    game_state.acknowledge_game_state(&connection.uuid, state_ack_message.tick);
+                }
+                NetworkMessage::RequestResync(_) => {
+                    let mut game_state = cloned_game_state.lock().await;
+                    game_state.request_resync(&connection.uuid);
+                }
+                _ => log::warn!("Received unexpected message!"),
             }
         }
-        Err(err) => println!("Error during parsing of message: {:?}", err),
+        Err(err) => log::warn!("Error during parsing of message: {:?}", err),
+    };
+}
+
+/// Adds the player (or spectator) to the given lobby and tells everyone inside about it.
+/// A player that does not fit into the lobby anymore stays outside of it.
+async fn enter_lobby(
+    connection: &mut Connection,
+    lobby_id: String,
+    game_state: Arc<Mutex<GameState>>,
+    as_spectator: bool,
+) {
+    {
+        let mut game_state = game_state.lock().await;
+        if game_state.is_closed() {
+            drop(game_state);
+            reject_request(connection, "The lobby was closed".to_string()).await;
+            send_connection_info_without_lobby(connection).await;
+            return;
+        }
+
+        if as_spectator {
+            game_state.connecting_spectator(
+                &connection.uuid,
+                &connection.display_name,
+                Arc::clone(&connection.write_socket),
+                &connection.resume_token,
+            );
+        } else {
+            let result = game_state.connecting_player(
+                &connection.uuid,
+                &connection.display_name,
+                connection.preferred_color,
+                Arc::clone(&connection.write_socket),
+                &connection.resume_token,
+            );
+
+            if let Err(reason) = result {
+                drop(game_state);
+                reject_request(connection, reason).await;
+                send_connection_info_without_lobby(connection).await;
+                return;
+            }
+        }
+        game_state.notify_about_player_joining().await;
+    }
+
+    connection.lobby = Some((lobby_id, game_state));
+}
+
+/// Removes the player from his current lobby - the lobby is closed if he was the last one inside
+async fn leave_lobby(lobby_registry: &Arc<Mutex<LobbyRegistry>>, connection: &mut Connection) {
+    if let Some((lobby_id, game_state)) = connection.lobby.take() {
+        {
+            let mut game_state = game_state.lock().await;
+            game_state.disconnecting_player(&connection.uuid);
+            game_state.notify_about_player_joining().await;
+        }
+
+        lobby_registry
+            .lock()
+            .await
+            .close_lobby_if_empty(&lobby_id)
+            .await;
+    }
+}
+
+/// Keeps the slot of the player for a while - he can take it back with his resume-token
+async fn suspend_session(lobby_registry: &Arc<Mutex<LobbyRegistry>>, connection: &mut Connection) {
+    let Some((lobby_id, game_state)) = connection.lobby.take() else {
+        return;
+    };
+
+    {
+        let mut game_state = game_state.lock().await;
+        game_state.suspend_player(&connection.uuid);
+        game_state.notify_about_lobby_status().await;
+    }
+
+    lobby_registry.lock().await.suspend_session(
+        &connection.resume_token,
+        SuspendedSession {
+            uuid: connection.uuid.clone(),
+            display_name: connection.display_name.clone(),
+            preferred_color: connection.preferred_color,
+            lobby_id,
+        },
+    );
+    log::info!("Player {} lost his connection", connection.uuid);
+
+    // the player is removed for good if he does not come back in time
+    let lobby_registry = Arc::clone(lobby_registry);
+    let resume_token = connection.resume_token.clone();
+    tokio::spawn(async move {
+        sleep(RESUME_GRACE_PERIOD).await;
+        expire_session(&lobby_registry, &resume_token).await;
+    });
+}
+
+/// Removes the player of the session from his lobby - unless he already came back
+async fn expire_session(lobby_registry: &Arc<Mutex<LobbyRegistry>>, resume_token: &str) {
+    // the registry is only locked for looking things up - one slow player must not stall the whole server
+    let (session, game_state) = {
+        let mut lobby_registry = lobby_registry.lock().await;
+        let Some(session) = lobby_registry.take_suspended_session(resume_token) else {
+            return;
+        };
+        let game_state = lobby_registry.get_lobby(&session.lobby_id);
+        (session, game_state)
     };
+
+    if let Some(game_state) = game_state {
+        let mut game_state = game_state.lock().await;
+        game_state.disconnecting_player(&session.uuid);
+        game_state.notify_about_player_joining().await;
+    }
+    log::info!("Player {} did not come back in time", session.uuid);
+
+    lobby_registry
+        .lock()
+        .await
+        .close_lobby_if_empty(&session.lobby_id)
+        .await;
+}
+
+/// Gives the player the slot back he had before losing his connection
+async fn resume_session(
+    lobby_registry: &Arc<Mutex<LobbyRegistry>>,
+    connection: &mut Connection,
+    resume_token: &str,
+) -> bool {
+    let (session, game_state) = {
+        let mut lobby_registry = lobby_registry.lock().await;
+        let Some(session) = lobby_registry.take_suspended_session(resume_token) else {
+            log::info!("Resume-token of {} is unknown or expired", connection.uuid);
+            return false;
+        };
+        let Some(game_state) = lobby_registry.get_lobby(&session.lobby_id) else {
+            return false;
+        };
+        (session, game_state)
+    };
+
+    connection.uuid = session.uuid;
+    connection.display_name = session.display_name;
+    connection.preferred_color = session.preferred_color;
+
+    {
+        let mut game_state = game_state.lock().await;
+        game_state.resume_player(
+            &connection.uuid,
+            Arc::clone(&connection.write_socket),
+            &connection.resume_token,
+        );
+        game_state.notify_about_player_joining().await;
+    }
+    log::info!("Player {} resumed his session", connection.uuid);
+
+    connection.lobby = Some((session.lobby_id, game_state));
+    true
+}
+
+/// Tells the player why we did not do what he asked for
+async fn reject_request(connection: &Connection, reason: String) {
+    log::info!("Rejected request of {}: {}", connection.uuid, reason);
+    let message = NetworkMessage::RequestRejected(RequestRejectedMessage { reason });
+    send_message(&connection.write_socket, &connection.uuid, &message).await;
+}
+
+async fn send_connection_info_without_lobby(connection: &Connection) {
+    let message = NetworkMessage::ConnectionInfo(ConnectionInfoMessage {
+        player_id: connection.uuid.clone(),
+        players_connected: 0,
+        lobby_id: None,
+        display_name: connection.display_name.clone(),
+        color: connection.preferred_color.unwrap_or_default(),
+        resume_token: connection.resume_token.clone(),
+        is_spectator: false,
+    });
+    send_message(&connection.write_socket, &connection.uuid, &message).await;
 }
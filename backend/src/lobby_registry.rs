@@ -0,0 +1,152 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_std::sync::Mutex;
+use shared::models::{
+    network_message::{LobbyInfo, LobbyListMessage, NetworkMessage},
+    player_color::PlayerColor,
+};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    game_state::{start_up_game_loop, GameState},
+};
+
+// players that lost their connection keep their slot this long
+pub const RESUME_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Slot of a player that lost his connection - he can take it back with his resume-token
+pub struct SuspendedSession {
+    pub uuid: String,
+    pub display_name: String,
+    pub preferred_color: Option<PlayerColor>,
+    pub lobby_id: String,
+}
+
+/// A single lobby on the server - every lobby runs its own game-loop
+pub struct Lobby {
+    pub name: String,
+    pub game_state: Arc<Mutex<GameState>>,
+    game_loop: JoinHandle<()>,
+}
+
+/// Registry that holds all the lobbies that are currently open on the server
+pub struct LobbyRegistry {
+    config: Arc<Config>, // every new lobby is created with it
+    lobbies: HashMap<String, Lobby>,
+    created_lobbies: u32,
+    suspended_sessions: HashMap<String, SuspendedSession>, // keyed by the resume-token
+}
+
+impl LobbyRegistry {
+    pub fn new(config: Arc<Config>) -> Self {
+        LobbyRegistry {
+            config,
+            lobbies: HashMap::new(),
+            created_lobbies: 0,
+            suspended_sessions: HashMap::new(),
+        }
+    }
+
+    /// Opens up a new lobby and starts its game-loop
+    pub fn create_lobby(&mut self, name: &str) -> (String, Arc<Mutex<GameState>>) {
+        self.created_lobbies += 1;
+
+        let lobby_id = Uuid::new_v4().to_string();
+        let name = match name.trim() {
+            "" => format!("Lobby {}", self.created_lobbies),
+            name => name.to_string(),
+        };
+
+        let game_state = Arc::new(Mutex::new(GameState::new(
+            &lobby_id,
+            self.config.game,
+            self.config.max_players_per_lobby,
+        )));
+        let game_loop = start_up_game_loop(Arc::clone(&game_state));
+
+        self.lobbies.insert(
+            lobby_id.clone(),
+            Lobby {
+                name,
+                game_state: Arc::clone(&game_state),
+                game_loop,
+            },
+        );
+
+        (lobby_id, game_state)
+    }
+
+    pub fn get_lobby(&self, lobby_id: &str) -> Option<Arc<Mutex<GameState>>> {
+        self.lobbies
+            .get(lobby_id)
+            .map(|lobby| Arc::clone(&lobby.game_state))
+    }
+
+    pub fn suspend_session(&mut self, resume_token: &str, session: SuspendedSession) {
+        self.suspended_sessions
+            .insert(resume_token.to_string(), session);
+    }
+
+    /// Takes the session out of the registry - a token can only be used once
+    pub fn take_suspended_session(&mut self, resume_token: &str) -> Option<SuspendedSession> {
+        self.suspended_sessions.remove(resume_token)
+    }
+
+    /// Closes the lobby and stops its game-loop if nobody is left inside
+    pub async fn close_lobby_if_empty(&mut self, lobby_id: &str) {
+        // players that are joining right now check the flag, they do not hold the registry
+        let is_empty = match self.lobbies.get(lobby_id) {
+            Some(lobby) => {
+                let mut game_state = lobby.game_state.lock().await;
+                if game_state.is_empty() {
+                    game_state.close();
+                }
+                game_state.is_closed()
+            }
+            None => false,
+        };
+
+        if is_empty {
+            if let Some(lobby) = self.lobbies.remove(lobby_id) {
+                lobby.game_loop.abort();
+            }
+        }
+    }
+
+    /// Id, name and game-state of every open lobby - the game-states can be locked after the registry is released
+    pub fn open_lobbies(&self) -> Vec<(String, String, Arc<Mutex<GameState>>)> {
+        self.lobbies
+            .iter()
+            .map(|(lobby_id, lobby)| {
+                (
+                    lobby_id.clone(),
+                    lobby.name.clone(),
+                    Arc::clone(&lobby.game_state),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Lists all open lobbies - the registry is not locked while waiting for the lobbies
+pub async fn get_lobby_list_message(lobby_registry: &Arc<Mutex<LobbyRegistry>>) -> NetworkMessage {
+    let open_lobbies = lobby_registry.lock().await.open_lobbies();
+    let mut lobbies = Vec::new();
+
+    for (lobby_id, name, game_state) in open_lobbies {
+        let game_state = game_state.lock().await;
+        lobbies.push(LobbyInfo {
+            lobby_id,
+            name,
+            players_connected: game_state.game_match.simulation.players.len() as u32,
+            lobby_state: game_state.game_match.lobby_state.clone(),
+        });
+    }
+
+    // keep the order stable for the clients
+    lobbies.sort_by(|a, b| a.name.cmp(&b.name));
+
+    NetworkMessage::LobbyList(LobbyListMessage { lobbies })
+}
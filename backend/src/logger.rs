@@ -0,0 +1,32 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Prints every message that is important enough to the console - errors go to stderr
+struct ConsoleLogger;
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        match record.level() {
+            log::Level::Error => eprintln!("[{}] {}", record.level(), record.args()),
+            _ => println!("[{}] {}", record.level(), record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: ConsoleLogger = ConsoleLogger;
+
+/// Only messages up to the given level are printed from now on
+pub fn init(level: LevelFilter) {
+    // there is only one logger - setting it a second time just keeps the first one
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level);
+}
@@ -1,25 +1,53 @@
-use async_std::sync::Mutex;
-use game_state::{start_up_game_loop, GameState};
+use std::{env, process, sync::Arc};
+
+use async_std::{net::TcpListener, sync::Mutex};
+use config::{Config, USAGE};
 use incoming_networking::spawn_up_listener_thread;
-use shared::models::player_states::LobbyState;
-use std::{collections::HashMap, sync::Arc};
+use lobby_registry::LobbyRegistry;
 
+mod config;
 mod game_state;
 mod incoming_networking;
+mod lobby_registry;
+mod logger;
+mod outgoing_networking;
+mod replay_recorder;
 
 #[tokio::main]
 async fn main() {
-    // firing up a clean game_state
-    let game_state = Arc::new(Mutex::new(GameState {
-        players: Vec::new(),
-        lobby_state: LobbyState::Waiting,
-        player_write_sockets: HashMap::new(),
-    }));
+    let args: Vec<String> = env::args().skip(1).collect();
+    if config::is_help_requested(&args) {
+        println!("{}", USAGE);
+        return;
+    }
+
+    // a broken config is reported before anything is started
+    let config = match Config::load(args.into_iter()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+    logger::init(config.log_level);
+
+    let server = match TcpListener::bind((config.bind_address, config.port)).await {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!(
+                "Could not listen on {}:{}: {}",
+                config.bind_address, config.port, err
+            );
+            process::exit(1);
+        }
+    };
+    log::info!("Listening on {}:{}", config.bind_address, config.port);
+
+    // firing up an empty lobby registry - every lobby runs its own game-loop
+    let lobby_registry = Arc::new(Mutex::new(LobbyRegistry::new(Arc::new(config))));
 
     // add the listener for new connections
-    let listener_thread = spawn_up_listener_thread(Arc::clone(&game_state));
-    // start the game-loop
-    start_up_game_loop(game_state);
+    let listener_thread = spawn_up_listener_thread(server, lobby_registry);
 
     // keep main-thread running as long as the listener-thread is up
     let _ = listener_thread.await;
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use async_std::{net::TcpStream, sync::Mutex};
+use async_tungstenite::{tungstenite::Message, WebSocketStream};
+use futures::{stream::SplitSink, SinkExt};
+use shared::models::{
+    network_message::NetworkMessage,
+    wire_format::{EncodedMessage, WireFormat},
+};
+
+/// Write-half of a websocket-connection together with the format the player wants to receive
+pub struct ClientSocket {
+    pub sink: SplitSink<WebSocketStream<TcpStream>, Message>,
+    pub wire_format: WireFormat,
+}
+
+/// The socket is shared between the connection-thread and the lobby the player is currently in.
+pub type WriteSocket = Arc<Mutex<ClientSocket>>;
+
+/// Encodes the message in the format of the player and sends it over the given socket
+pub async fn send_message(write_socket: &WriteSocket, uuid: &str, message: &NetworkMessage) {
+    let mut write_socket = write_socket.lock().await;
+
+    let message = match message.encode(write_socket.wire_format) {
+        Ok(EncodedMessage::Text(text)) => Message::Text(text),
+        Ok(EncodedMessage::Binary(bytes)) => Message::Binary(bytes),
+        Err(err) => {
+            log::error!("Failed to serialize message for {}: {}", uuid, err);
+            return;
+        }
+    };
+
+    if let Err(e) = write_socket.sink.send(message).await {
+        log::warn!("Failed to send message to {}: {:?}", uuid, e);
+    }
+}
@@ -0,0 +1,71 @@
+use std::{collections::HashMap, path::Path};
+
+use shared::models::{
+    game_settings::GameSettings, network_message::GameStateMessage, player_states::LobbyState,
+    replay::Replay,
+};
+
+// replays are written relative to the working directory of the backend
+const REPLAY_DIRECTORY: &str = "replays";
+
+/// Records the game-states of a running match, so it can be played back afterwards.
+/// Every frame only contains the trail points that were added since the frame before.
+pub struct ReplayRecorder {
+    replay: Replay,
+    trail_lengths: Option<HashMap<String, usize>>, // trail lengths of the last frame - none means the next one is a keyframe
+    last_lobby_state: Option<LobbyState>,
+}
+
+impl ReplayRecorder {
+    pub fn new(lobby_id: &str, settings: GameSettings, recorded_at_millis: u64) -> Self {
+        Self {
+            replay: Replay {
+                lobby_id: lobby_id.to_string(),
+                settings,
+                recorded_at_millis,
+                frames: Vec::new(),
+            },
+            trail_lengths: None,
+            last_lobby_state: None,
+        }
+    }
+
+    /// The trails were reset or erased - the next frame is a keyframe, so the playback can be started from there
+    pub fn start_keyframe(&mut self) {
+        self.trail_lengths = None;
+    }
+
+    pub fn trail_lengths(&self) -> Option<&HashMap<String, usize>> {
+        self.trail_lengths.as_ref()
+    }
+
+    /// Only the running game is recorded every tick - the countdown and the scoreboard only when they change
+    pub fn wants_frame(&self, lobby_state: &LobbyState) -> bool {
+        *lobby_state == LobbyState::Running || self.last_lobby_state.as_ref() != Some(lobby_state)
+    }
+
+    pub fn push_frame(&mut self, frame: GameStateMessage, trail_lengths: HashMap<String, usize>) {
+        self.last_lobby_state = Some(frame.lobby_state.clone());
+        self.trail_lengths = Some(trail_lengths);
+        self.replay.frames.push(frame);
+    }
+
+    pub fn finish(self) -> Replay {
+        self.replay
+    }
+}
+
+pub fn save_replay(replay: &Replay) {
+    match replay.save(Path::new(REPLAY_DIRECTORY)) {
+        Ok(path) => log::info!(
+            "Saved replay of lobby {} to {}",
+            replay.lobby_id,
+            path.display()
+        ),
+        Err(err) => log::error!(
+            "Could not save replay of lobby {}: {}",
+            replay.lobby_id,
+            err
+        ),
+    }
+}
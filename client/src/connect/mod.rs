@@ -0,0 +1,443 @@
+use std::{env, fs};
+
+use bevy::{
+    app::AppExit,
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    despawn_screen,
+    networking::{
+        setup_network_client, websocket_url, NetworkClient, NetworkEvent, UnboundedReceiverResource,
+    },
+    player::ConnectionInfo,
+    GameState,
+};
+
+// the servers we connected to are kept relative to the working directory of the client
+const RECENT_SERVERS_FILE: &str = "recent_servers.json";
+const MAX_RECENT_SERVERS: usize = 5;
+const MAX_ADDRESS_LENGTH: usize = 100;
+
+// the server can also be given on start - the flag wins over the environment-variable
+const SERVER_FLAG: &str = "--server";
+const SERVER_ENV_VARIABLE: &str = "DATADRIFT_SERVER";
+const DEFAULT_ADDRESS: &str = "localhost";
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const SERVER_BUTTON: Color = Color::srgb(0.2, 0.2, 0.3);
+const ERROR_COLOR: Color = Color::srgb(1.0, 0.4, 0.4);
+
+pub fn connect_plugin(app: &mut App) {
+    app.insert_resource(RecentServers::load())
+        .init_resource::<ServerSelection>()
+        .add_systems(OnEnter(GameState::Connect), connect_setup)
+        .add_systems(
+            Update,
+            (
+                edit_address,
+                connect_action,
+                check_connection,
+                update_address_text,
+                update_status_text,
+            )
+                .run_if(in_state(GameState::Connect)),
+        )
+        .add_systems(
+            OnExit(GameState::Connect),
+            despawn_screen::<OnConnectScreen>,
+        );
+}
+
+#[derive(Component)]
+struct OnConnectScreen;
+
+#[derive(Component)]
+struct AddressText;
+
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Component)]
+enum ConnectButtonAction {
+    Connect,
+    RecentServer(String),
+    Quit,
+}
+
+/// Servers we were connected to - the newest one comes first
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct RecentServers {
+    addresses: Vec<String>,
+}
+
+impl RecentServers {
+    // a missing or broken file just means we do not know any servers yet
+    fn load() -> Self {
+        fs::read_to_string(RECENT_SERVERS_FILE)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Puts the address in front of the list and saves it for the next start
+    fn remember(&mut self, address: &str) {
+        self.addresses.retain(|known| known != address);
+        self.addresses.insert(0, address.to_string());
+        self.addresses.truncate(MAX_RECENT_SERVERS);
+
+        let result = serde_json::to_string_pretty(self)
+            .map_err(|err| err.to_string())
+            .and_then(|content| {
+                fs::write(RECENT_SERVERS_FILE, content).map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            println!("Could not save the recent servers: {}", err);
+        }
+    }
+}
+
+/// Address the player is typing in and how connecting to it goes
+#[derive(Resource, Default)]
+struct ServerSelection {
+    address: String,
+    connecting_to: Option<String>, // we wait for the backend of this address to greet us
+    status: String,                // progress or why the last attempt failed
+    is_error: bool,
+    is_override_used: bool, // the server given on start is only connected to automatically once
+}
+
+// `--server <address>` or the environment-variable
+fn address_override() -> Option<String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == SERVER_FLAG {
+            return args.next();
+        }
+    }
+    env::var(SERVER_ENV_VARIABLE).ok()
+}
+
+fn connect_setup(
+    mut commands: Commands,
+    mut selection: ResMut<ServerSelection>,
+    recent_servers: Res<RecentServers>,
+    network_client: Option<ResMut<NetworkClient>>,
+) {
+    let address_override = if selection.is_override_used {
+        None
+    } else {
+        address_override()
+    };
+    selection.is_override_used = true;
+
+    if selection.address.is_empty() {
+        selection.address = recent_servers
+            .addresses
+            .first()
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    }
+
+    // the server given on start is connected to right away
+    if let Some(address) = address_override {
+        selection.address = address.clone();
+        start_connecting(&mut commands, &mut selection, network_client, &address);
+    }
+
+    let button_node = Node {
+        width: Val::Px(300.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let server_button_node = Node {
+        width: Val::Px(400.0),
+        height: Val::Px(40.0),
+        margin: UiRect::all(Val::Px(5.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_font = TextFont {
+        font_size: 33.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.31, 0.31, 0.31)),
+            OnConnectScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("DataDrift"),
+                TextFont {
+                    font_size: 67.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            // address of the server - can be changed by typing
+            parent
+                .spawn((
+                    Text::new("Server: "),
+                    button_text_font.clone(),
+                    TextColor(Color::WHITE),
+                ))
+                .with_child((
+                    TextSpan::default(),
+                    button_text_font.clone(),
+                    TextColor(Color::WHITE),
+                    AddressText,
+                ));
+
+            // connecting again is the same as retrying
+            parent
+                .spawn((
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(NORMAL_BUTTON),
+                    ConnectButtonAction::Connect,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Connect"),
+                        button_text_font.clone(),
+                        TextColor(Color::WHITE),
+                    ));
+                });
+
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 26.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                StatusText,
+            ));
+
+            // servers we were connected to before
+            if !recent_servers.addresses.is_empty() {
+                parent.spawn((
+                    Text::new("Recent servers"),
+                    TextFont {
+                        font_size: 26.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    Node {
+                        margin: UiRect::top(Val::Px(20.0)),
+                        ..default()
+                    },
+                ));
+            }
+            for address in recent_servers.addresses.iter() {
+                parent
+                    .spawn((
+                        Button,
+                        server_button_node.clone(),
+                        BackgroundColor(SERVER_BUTTON),
+                        ConnectButtonAction::RecentServer(address.clone()),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Text::new(address.clone()),
+                            TextFont {
+                                font_size: 22.0,
+                                ..default()
+                            },
+                            TextColor(Color::WHITE),
+                        ));
+                    });
+            }
+
+            parent
+                .spawn((
+                    Button,
+                    button_node,
+                    BackgroundColor(NORMAL_BUTTON),
+                    ConnectButtonAction::Quit,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((Text::new("Quit"), button_text_font, TextColor(Color::WHITE)));
+                });
+        });
+}
+
+/// Replaces the network-client we might have with one for the given address
+fn start_connecting(
+    commands: &mut Commands,
+    selection: &mut ServerSelection,
+    network_client: Option<ResMut<NetworkClient>>,
+    address: &str,
+) {
+    let url = match websocket_url(address) {
+        Ok(url) => url,
+        Err(err) => {
+            selection.status = err;
+            selection.is_error = true;
+            return;
+        }
+    };
+
+    // the last attempt might still be running - it must not keep reconnecting in the background
+    if let Some(mut network_client) = network_client {
+        network_client.disconnect();
+    }
+    commands.remove_resource::<ConnectionInfo>();
+
+    setup_network_client(commands, url.clone());
+    selection.connecting_to = Some(address.to_string());
+    selection.status = format!("Connecting to {} ...", url);
+    selection.is_error = false;
+}
+
+/// Typing changes the address, enter connects to it
+fn edit_address(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut selection: ResMut<ServerSelection>,
+    mut network_client: Option<ResMut<NetworkClient>>,
+) {
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(characters) => {
+                for character in characters.chars() {
+                    if selection.address.chars().count() < MAX_ADDRESS_LENGTH {
+                        selection.address.push(character);
+                    }
+                }
+            }
+            Key::Backspace => {
+                selection.address.pop();
+            }
+            Key::Enter => {
+                let address = selection.address.clone();
+                start_connecting(
+                    &mut commands,
+                    &mut selection,
+                    network_client.take(),
+                    &address,
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+fn connect_action(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &ConnectButtonAction), Changed<Interaction>>,
+    mut app_exit_events: EventWriter<AppExit>,
+    mut selection: ResMut<ServerSelection>,
+    mut network_client: Option<ResMut<NetworkClient>>,
+) {
+    for (interaction, button_action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button_action {
+            ConnectButtonAction::Connect => {
+                let address = selection.address.clone();
+                start_connecting(
+                    &mut commands,
+                    &mut selection,
+                    network_client.take(),
+                    &address,
+                );
+            }
+            ConnectButtonAction::RecentServer(address) => {
+                selection.address = address.clone();
+                start_connecting(
+                    &mut commands,
+                    &mut selection,
+                    network_client.take(),
+                    address,
+                );
+            }
+            ConnectButtonAction::Quit => {
+                app_exit_events.send(AppExit::Success);
+            }
+        }
+    }
+}
+
+/// As soon as the backend greeted us we are in - if it can not be reached, we tell the player why
+fn check_connection(
+    mut commands: Commands,
+    mut selection: ResMut<ServerSelection>,
+    mut recent_servers: ResMut<RecentServers>,
+    mut network_events: EventReader<NetworkEvent>,
+    connection_info: Option<Res<ConnectionInfo>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let connect_error = network_events
+        .read()
+        .filter_map(|network_event| match network_event {
+            NetworkEvent::Disconnected(reason) => Some(reason.clone()),
+            _ => None,
+        })
+        .last();
+    let Some(address) = selection.connecting_to.clone() else {
+        return;
+    };
+
+    if connection_info.is_some() {
+        recent_servers.remember(&address);
+        selection.connecting_to = None;
+        selection.status = String::new();
+        game_state.set(GameState::Menu);
+        return;
+    }
+
+    if let Some(err) = connect_error {
+        commands.remove_resource::<NetworkClient>();
+        commands.remove_resource::<UnboundedReceiverResource>();
+        selection.connecting_to = None;
+        selection.status = format!("Could not connect to {}: {}", address, err);
+        selection.is_error = true;
+    }
+}
+
+fn update_address_text(
+    mut query: Query<&mut TextSpan, With<AddressText>>,
+    selection: Res<ServerSelection>,
+) {
+    for mut span in &mut query {
+        **span = format!("{}_", selection.address);
+    }
+}
+
+fn update_status_text(
+    mut query: Query<(&mut Text, &mut TextColor), With<StatusText>>,
+    selection: Res<ServerSelection>,
+) {
+    for (mut text, mut text_color) in &mut query {
+        **text = selection.status.clone();
+        text_color.0 = if selection.is_error {
+            ERROR_COLOR
+        } else {
+            Color::WHITE
+        };
+    }
+}
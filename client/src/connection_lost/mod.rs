@@ -0,0 +1,208 @@
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{
+    despawn_screen,
+    game::FrontendLobbyState,
+    networking::{NetworkClient, NetworkEvent, UnboundedReceiverResource},
+    player::ConnectionInfo,
+    replay::ReplayPlayback,
+    GameState,
+};
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+
+pub fn connection_lost_plugin(app: &mut App) {
+    app.init_resource::<LostConnection>()
+        .add_systems(
+            Update,
+            show_connection_lost.run_if(
+                in_state(GameState::Menu)
+                    .or(in_state(GameState::Game))
+                    .and(resource_exists::<NetworkClient>),
+            ),
+        )
+        .add_systems(OnEnter(GameState::ConnectionLost), connection_lost_setup)
+        .add_systems(
+            Update,
+            (
+                wait_for_reconnect,
+                connection_lost_action,
+                update_reason_text,
+            )
+                .run_if(in_state(GameState::ConnectionLost)),
+        )
+        .add_systems(
+            OnExit(GameState::ConnectionLost),
+            despawn_screen::<OnConnectionLostScreen>,
+        );
+}
+
+#[derive(Component)]
+struct OnConnectionLostScreen;
+
+#[derive(Component)]
+struct ReasonText;
+
+#[derive(Component)]
+enum ConnectionLostButtonAction {
+    ChangeServer,
+    Quit,
+}
+
+/// Why the connection to the backend was lost
+#[derive(Resource, Default)]
+struct LostConnection {
+    reason: String,
+}
+
+/// Leaves the menu or the match as soon as the connection is gone - replays are watched without it
+fn show_connection_lost(
+    mut network_events: EventReader<NetworkEvent>,
+    mut lost_connection: ResMut<LostConnection>,
+    replay_playback: Option<Res<ReplayPlayback>>,
+    mut lobby_state: ResMut<NextState<FrontendLobbyState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for network_event in network_events.read() {
+        if let NetworkEvent::Disconnected(reason) = network_event {
+            if replay_playback.is_some() {
+                continue;
+            }
+
+            lost_connection.reason = reason.clone();
+            lobby_state.set(FrontendLobbyState::Loading);
+            game_state.set(GameState::ConnectionLost);
+        }
+    }
+}
+
+fn connection_lost_setup(mut commands: Commands) {
+    let button_node = Node {
+        width: Val::Px(300.0),
+        height: Val::Px(65.0),
+        margin: UiRect::all(Val::Px(20.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_font = TextFont {
+        font_size: 33.0,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.31, 0.31, 0.31)),
+            OnConnectionLostScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Connection lost"),
+                TextFont {
+                    font_size: 67.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            // filled by update_reason_text - it also tells whether we are still trying
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 26.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                TextLayout::new_with_justify(JustifyText::Center),
+                ReasonText,
+            ));
+
+            for (action, label) in [
+                (ConnectionLostButtonAction::ChangeServer, "Change Server"),
+                (ConnectionLostButtonAction::Quit, "Quit"),
+            ] {
+                parent
+                    .spawn((
+                        Button,
+                        button_node.clone(),
+                        BackgroundColor(NORMAL_BUTTON),
+                        action,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Text::new(label),
+                            button_text_font.clone(),
+                            TextColor(Color::WHITE),
+                        ));
+                    });
+            }
+        });
+}
+
+/// Back to the menu once the backend is reachable again - it tells us if we still have our slot
+fn wait_for_reconnect(
+    mut network_events: EventReader<NetworkEvent>,
+    mut lost_connection: ResMut<LostConnection>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for network_event in network_events.read() {
+        match network_event {
+            NetworkEvent::Connected => game_state.set(GameState::Menu),
+            NetworkEvent::Disconnected(reason) => lost_connection.reason = reason.clone(),
+            NetworkEvent::ProtocolError(_) => {}
+        }
+    }
+}
+
+fn connection_lost_action(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &ConnectionLostButtonAction), Changed<Interaction>>,
+    mut app_exit_events: EventWriter<AppExit>,
+    mut network_client: Option<ResMut<NetworkClient>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, button_action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button_action {
+            ConnectionLostButtonAction::ChangeServer => {
+                // stop trying - the connect screen creates a new network-client
+                if let Some(mut network_client) = network_client.take() {
+                    network_client.disconnect();
+                }
+                commands.remove_resource::<NetworkClient>();
+                commands.remove_resource::<UnboundedReceiverResource>();
+                commands.remove_resource::<ConnectionInfo>();
+                game_state.set(GameState::Connect);
+            }
+            ConnectionLostButtonAction::Quit => {
+                app_exit_events.send(AppExit::Success);
+            }
+        }
+    }
+}
+
+fn update_reason_text(
+    mut query: Query<&mut Text, With<ReasonText>>,
+    lost_connection: Res<LostConnection>,
+    network_client: Option<Res<NetworkClient>>,
+) {
+    let status = match network_client {
+        Some(network_client) if network_client.is_reconnecting() => "Reconnecting ...",
+        _ => "The backend could not be reached again",
+    };
+
+    for mut text in &mut query {
+        **text = format!("{}\n{}", lost_connection.reason, status);
+    }
+}
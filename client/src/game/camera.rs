@@ -0,0 +1,188 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    player::{ConnectionInfo, Player},
+    replay::ReplayPlayback,
+    BackendState,
+};
+
+use super::OnGameScreen;
+
+// while following a player we zoom in, so his surroundings are easier to see
+const FOLLOW_ZOOM: f32 = 0.6;
+
+/// What the camera shows - only spectators, replay viewers and eliminated players can choose it
+#[derive(Resource, Default, PartialEq)]
+pub enum CameraMode {
+    #[default]
+    WholeBoard,
+    FollowPlayer(String), // id of the player the camera stays on
+}
+
+#[derive(Component)]
+pub struct CameraHintText;
+
+// spectators, replay viewers and players that are already out of the round can look around
+fn can_choose_camera(
+    backend_state: &BackendState,
+    connection_info: &ConnectionInfo,
+    is_watching_replay: bool,
+) -> bool {
+    is_watching_replay
+        || connection_info.is_spectator
+        || backend_state
+            .players
+            .iter()
+            .find(|p| p.id == connection_info.uuid)
+            .is_none_or(|p| !p.is_alive)
+}
+
+/// The controls of the camera are shown in the top right corner
+pub fn setup_camera_hint(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 22.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            right: Val::Px(12.0),
+            ..default()
+        },
+        CameraHintText,
+        OnGameScreen,
+    ));
+}
+
+// boards that are bigger than the window are zoomed out until they fit
+fn whole_board_scale(backend_state: &BackendState, window: Option<&Window>) -> f32 {
+    let Some(window) = window else {
+        return 1.0;
+    };
+
+    let settings = &backend_state.settings;
+    (settings.board_width / window.width())
+        .max(settings.board_height / window.height())
+        .max(1.0)
+}
+
+/// Every round starts with the whole board in view
+pub fn reset_camera(
+    mut camera_mode: ResMut<CameraMode>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    backend_state: Res<BackendState>,
+) {
+    *camera_mode = CameraMode::WholeBoard;
+    let scale = whole_board_scale(&backend_state, window_query.get_single().ok());
+
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        transform.translation.x = 0.0;
+        transform.translation.y = 0.0;
+        projection.scale = scale;
+    }
+}
+
+/// Tab follows the next player that is still alive, B shows the whole board again
+pub fn choose_camera_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut camera_mode: ResMut<CameraMode>,
+    backend_state: Res<BackendState>,
+    connection_info: Res<ConnectionInfo>,
+    replay_playback: Option<Res<ReplayPlayback>>,
+) {
+    if !can_choose_camera(&backend_state, &connection_info, replay_playback.is_some()) {
+        if *camera_mode != CameraMode::WholeBoard {
+            *camera_mode = CameraMode::WholeBoard;
+        }
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::KeyB) {
+        *camera_mode = CameraMode::WholeBoard;
+    }
+
+    if keys.just_pressed(KeyCode::Tab) {
+        let alive_players: Vec<&str> = backend_state
+            .players
+            .iter()
+            .filter(|p| p.is_alive)
+            .map(|p| p.id.as_str())
+            .collect();
+
+        // start over with the first player after the last one
+        let next_index = match &*camera_mode {
+            CameraMode::FollowPlayer(followed_id) => alive_players
+                .iter()
+                .position(|id| id == followed_id)
+                .map_or(0, |index| (index + 1) % alive_players.len()),
+            CameraMode::WholeBoard => 0,
+        };
+
+        if let Some(next_player) = alive_players.get(next_index) {
+            *camera_mode = CameraMode::FollowPlayer(next_player.to_string());
+        }
+    }
+}
+
+/// Keeps the camera on the followed player - without one it shows the whole board
+pub fn move_camera(
+    camera_mode: Res<CameraMode>,
+    players: Query<(&Player, &Transform), Without<Camera2d>>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    backend_state: Res<BackendState>,
+) {
+    let followed_position = match &*camera_mode {
+        CameraMode::FollowPlayer(followed_id) => players
+            .iter()
+            .find(|(player, _)| &player.uuid == followed_id)
+            .map(|(_, transform)| transform.translation.truncate()),
+        CameraMode::WholeBoard => None,
+    };
+
+    let whole_board_scale = whole_board_scale(&backend_state, window_query.get_single().ok());
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        let (position, scale) = match followed_position {
+            Some(position) => (position, FOLLOW_ZOOM),
+            None => (Vec2::ZERO, whole_board_scale),
+        };
+
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        projection.scale = scale;
+    }
+}
+
+pub fn update_camera_hint_text(
+    mut query: Query<&mut Text, With<CameraHintText>>,
+    camera_mode: Res<CameraMode>,
+    backend_state: Res<BackendState>,
+    connection_info: Res<ConnectionInfo>,
+    replay_playback: Option<Res<ReplayPlayback>>,
+) {
+    let hint = if !can_choose_camera(&backend_state, &connection_info, replay_playback.is_some()) {
+        String::new()
+    } else {
+        match &*camera_mode {
+            CameraMode::FollowPlayer(followed_id) => {
+                let name = backend_state
+                    .players
+                    .iter()
+                    .find(|p| &p.id == followed_id)
+                    .map_or("", |p| p.display_name.as_str());
+                format!("Spectating {}\nTab: next player, B: whole board", name)
+            }
+            CameraMode::WholeBoard => "Spectating\nTab: follow a player".to_string(),
+        }
+    };
+
+    for mut text in &mut query {
+        if text.0 != hint {
+            text.0 = hint.clone();
+        }
+    }
+}
@@ -1,19 +1,40 @@
 use bevy::prelude::*;
+use camera::{
+    choose_camera_mode, move_camera, reset_camera, setup_camera_hint, update_camera_hint_text,
+    CameraMode,
+};
+use scoreboard::{
+    setup_scoreboard, spawn_placement_list, spawn_score_table, update_next_round_text, OnScoreboard,
+};
+use shared::models::power_up::{EffectKind, PowerUpKind};
 
 use crate::{
     despawn_screen,
+    interpolation::{buffer_snapshot, SnapshotBuffer},
     player::{
-        move_player, spawn_players_according_to_backend, ConnectionInfo, Player, RenderedTrails,
+        move_player, move_player_labels, player_color, predict_own_player, reconcile_own_player,
+        spawn_players_according_to_backend, ConnectionInfo, OwnPlayerPrediction, Player,
+        RenderedTrail, RenderedTrails,
     },
+    replay::ReplayPlayback,
     BackendState, GameState,
 };
 
+mod camera;
+mod scoreboard;
+
+const GAP_PLAYER_ALPHA: f32 = 0.4;
+const TRAIL_RADIUS: f32 = 5.0;
+const THIN_TRAIL_RADIUS: f32 = 2.5;
+const POWER_UP_RADIUS: f32 = 12.0;
+
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 pub enum FrontendLobbyState {
     #[default]
     Loading,
     Countdown,
     Running,
+    RoundOver,
     Finished,
 }
 
@@ -23,71 +44,150 @@ pub struct EndTimer {
 }
 
 pub fn game_plugin(app: &mut App) {
-    app.add_systems(OnEnter(GameState::Game), game_setup)
+    app.add_systems(OnEnter(GameState::Game), (game_setup, setup_camera_hint))
         .init_state::<FrontendLobbyState>()
+        .init_resource::<OwnPlayerPrediction>()
+        .init_resource::<CameraMode>()
+        .init_resource::<SnapshotBuffer>()
         .add_systems(
             OnEnter(FrontendLobbyState::Countdown),
-            (setup_countdown, setup_players),
+            (
+                despawn_screen::<OnRound>,
+                reset_camera,
+                setup_countdown,
+                setup_players,
+            )
+                .chain(),
+        )
+        // we joined (or seeked a replay) into a running round - nobody was spawned by the countdown
+        .add_systems(
+            OnEnter(FrontendLobbyState::Running),
+            setup_players.run_if(no_players_spawned),
+        )
+        .add_systems(OnEnter(FrontendLobbyState::RoundOver), setup_scoreboard)
+        .add_systems(
+            Update,
+            update_next_round_text.run_if(in_state(FrontendLobbyState::RoundOver)),
+        )
+        .add_systems(
+            OnExit(FrontendLobbyState::RoundOver),
+            despawn_screen::<OnScoreboard>,
         )
         .add_systems(OnEnter(FrontendLobbyState::Finished), setup_finished)
+        .add_systems(
+            OnExit(FrontendLobbyState::Finished),
+            despawn_screen::<OnFinished>,
+        )
         .add_systems(
             Update,
             (update_countdown_text).run_if(in_state(FrontendLobbyState::Countdown)),
         )
+        .add_systems(
+            FixedUpdate,
+            predict_own_player.run_if(
+                in_state(FrontendLobbyState::Running).and(not(resource_exists::<ReplayPlayback>)),
+            ),
+        )
         .add_systems(
             Update,
-            (align_with_backend, move_player).run_if(in_state(FrontendLobbyState::Running)),
+            (
+                (
+                    reconcile_own_player.run_if(not(resource_exists::<ReplayPlayback>)),
+                    buffer_snapshot,
+                )
+                    .run_if(resource_changed::<BackendState>),
+                align_with_backend,
+                align_power_ups,
+                update_effects_text,
+                move_player,
+                move_player_labels,
+                choose_camera_mode,
+                move_camera,
+                update_camera_hint_text,
+            )
+                .chain()
+                .run_if(in_state(FrontendLobbyState::Running)),
         )
         .add_systems(
             Update,
-            check_quit.run_if(in_state(FrontendLobbyState::Finished)),
+            // a replay stays on the last screen until the viewer leaves it
+            check_quit.run_if(
+                in_state(FrontendLobbyState::Finished).and(not(resource_exists::<ReplayPlayback>)),
+            ),
         )
         .add_systems(
             OnExit(FrontendLobbyState::Countdown),
             despawn_screen::<OnCountdown>,
         )
-        .add_systems(OnExit(GameState::Game), despawn_screen::<OnGameScreen>);
+        .add_systems(
+            OnExit(GameState::Game),
+            (despawn_screen::<OnGameScreen>, reset_camera),
+        );
 }
 
 #[derive(Component)]
 pub struct OnGameScreen;
 
+/// Everything that belongs to a single round - it is removed before the next one starts
+#[derive(Component)]
+pub struct OnRound;
+
 #[derive(Component)]
 struct GameField;
 
+/// The winner of the match - a replay can go back to the rounds before
+#[derive(Component)]
+struct OnFinished;
+
 #[derive(Component)]
 struct OnCountdown;
 
 #[derive(Component)]
 struct CountdownText;
 
-fn game_setup(mut commands: Commands) {
-    commands
-        .spawn((
-            Node {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
-                flex_direction: FlexDirection::Column,
-                ..default()
-            },
-            OnGameScreen,
-            Transform::from_xyz(0., 0., -1.),
-        ))
-        .with_children(|parent| {
-            // Display the logo
-            parent.spawn((
-                Node {
-                    width: Val::Px(1000.0),
-                    height: Val::Px(1000.0),
+#[derive(Component)]
+struct TrailSegment {
+    player_id: String,
+}
 
-                    ..default()
-                },
-                BackgroundColor(Color::srgba(0.1, 0.1, 0.1, 0.5)),
-                GameField,
-            ));
-        });
+#[derive(Component)]
+struct PowerUpMarker {
+    id: u32,
+}
+
+#[derive(Component)]
+struct EffectsText;
+
+fn game_setup(mut commands: Commands, backend_state: Res<BackendState>) {
+    // the board is part of the world, so it moves along when the camera follows a player
+    let settings = &backend_state.settings;
+    commands.spawn((
+        Sprite::from_color(
+            Color::srgba(0.1, 0.1, 0.1, 0.5),
+            Vec2::new(settings.board_width, settings.board_height),
+        ),
+        Transform::from_xyz(0., 0., -1.),
+        GameField,
+        OnGameScreen,
+    ));
+
+    // the effects of our own player are shown in the top left corner
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 22.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        EffectsText,
+        OnGameScreen,
+    ));
 }
 
 fn setup_countdown(mut commands: Commands) {
@@ -140,57 +240,194 @@ fn setup_players(
     backend_state: Res<BackendState>,
     connection_info: Res<ConnectionInfo>,
     asset_server: Res<AssetServer>,
+    mut prediction: ResMut<OwnPlayerPrediction>,
+    mut snapshot_buffer: ResMut<SnapshotBuffer>,
+    replay_playback: Option<Res<ReplayPlayback>>,
 ) {
-    commands.insert_resource(RenderedTrails { count: 0 });
-    spawn_players_according_to_backend(commands, backend_state, connection_info, asset_server);
+    commands.insert_resource(RenderedTrails::default());
+    prediction.reset();
+    snapshot_buffer.clear();
+
+    // inside of a replay we only watch - even if we played in the recorded match
+    let own_player_id = match replay_playback {
+        Some(_) => None,
+        None => Some(connection_info.uuid.as_str()),
+    };
+    spawn_players_according_to_backend(commands, &backend_state, own_player_id, asset_server);
 }
 
+fn no_players_spawned(players: Query<(), With<Player>>) -> bool {
+    players.is_empty()
+}
+
+#[allow(clippy::too_many_arguments)] // bevy hands every resource in as its own parameter
 fn align_with_backend(
     mut commands: Commands,
-    mut query: Query<(&mut Transform, &mut Player)>,
+    mut query: Query<(&mut Player, &mut Sprite)>,
+    trail_segments: Query<(Entity, &TrailSegment)>,
     backend_state: Res<BackendState>,
     mut rendered_trails: ResMut<RenderedTrails>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     for backend_player in backend_state.players.iter() {
-        for (i, &trail_segment) in backend_player.trail.iter().enumerate() {
-            if (i as u32) < rendered_trails.count {
-                continue; // skip already rendered
+        let rendered_trail = rendered_trails
+            .trails
+            .entry(backend_player.id.clone())
+            .or_default();
+
+        // keyframes mostly repeat what we have - only a trail that got shorter or starts somewhere else
+        // was replaced (e.g. erased) and is drawn again from scratch
+        let first_point = backend_player.trail.first().map(|point| (point.x, point.y));
+        let is_replaced = backend_player.trail.len() < rendered_trail.count
+            || (rendered_trail.count > 0 && first_point != rendered_trail.first_point);
+        if is_replaced {
+            for (entity, segment) in &trail_segments {
+                if segment.player_id == backend_player.id {
+                    commands.entity(entity).despawn();
+                }
             }
+            *rendered_trail = RenderedTrail::default();
+        }
+
+        // skip already rendered - gaps in the trail simply have no points
+        for trail_point in backend_player.trail.iter().skip(rendered_trail.count) {
+            let radius = if trail_point.is_thin {
+                THIN_TRAIL_RADIUS
+            } else {
+                TRAIL_RADIUS
+            };
 
             commands.spawn((
-                Mesh2d(meshes.add(Circle::new(5.0))),
-                MeshMaterial2d(materials.add(Color::srgb(0.9, 0.2, 0.5))),
-                Transform::from_xyz(trail_segment.0, trail_segment.1, 0.0),
+                Mesh2d(meshes.add(Circle::new(radius))),
+                MeshMaterial2d(materials.add(player_color(backend_player.color))),
+                Transform::from_xyz(trail_point.x, trail_point.y, 0.0),
+                TrailSegment {
+                    player_id: backend_player.id.clone(),
+                },
+                OnRound,
                 OnGameScreen,
             ));
         }
+        rendered_trail.count = backend_player.trail.len();
+        rendered_trail.first_point = first_point;
 
-        // update position of player
-        for (mut player_pos, mut player) in &mut query {
+        // positions are not taken over directly - our own player is predicted, the others are interpolated
+        for (mut player, mut sprite) in &mut query {
             if player.uuid == backend_player.id {
-                let quat = Quat::from_rotation_z(backend_player.direction);
                 player.is_alive = backend_player.is_alive;
-                player.rotation = quat;
-                player_pos.translation.x = backend_player.position_x;
-                player_pos.translation.y = backend_player.position_y;
+
+                // players inside of a gap are shown faded
+                let alpha = if backend_player.is_drawing_trail {
+                    1.0
+                } else {
+                    GAP_PLAYER_ALPHA
+                };
+                sprite.color = player_color(backend_player.color).with_alpha(alpha);
             }
         }
     }
+}
+
+/// Spawns the power-ups that appeared on the board and removes the ones that were picked up
+fn align_power_ups(
+    mut commands: Commands,
+    query: Query<(Entity, &PowerUpMarker)>,
+    backend_state: Res<BackendState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, marker) in &query {
+        if !backend_state.power_ups.iter().any(|p| p.id == marker.id) {
+            commands.entity(entity).despawn();
+        }
+    }
 
-    if backend_state.players.len() > 0 {
-        if let Some(first_player) = backend_state.players.get(0) {
-            rendered_trails.count = first_player.trail.len() as u32;
+    for power_up in backend_state.power_ups.iter() {
+        if query.iter().any(|(_, marker)| marker.id == power_up.id) {
+            continue;
         }
+
+        commands.spawn((
+            Mesh2d(meshes.add(Circle::new(POWER_UP_RADIUS))),
+            MeshMaterial2d(materials.add(power_up_color(power_up.kind))),
+            Transform::from_xyz(power_up.position_x, power_up.position_y, 0.5),
+            PowerUpMarker { id: power_up.id },
+            OnRound,
+            OnGameScreen,
+        ));
+    }
+}
+
+fn power_up_color(kind: PowerUpKind) -> Color {
+    match kind {
+        PowerUpKind::SpeedBoost => Color::srgb(0.2, 0.9, 0.3),
+        PowerUpKind::SlowOthers => Color::srgb(0.2, 0.4, 0.9),
+        PowerUpKind::ThinTrail => Color::srgb(0.9, 0.9, 0.9),
+        PowerUpKind::Invincibility => Color::srgb(0.95, 0.8, 0.1),
+        PowerUpKind::TrailErase => Color::srgb(0.9, 0.4, 0.1),
+    }
+}
+
+/// Shows which effects are active on our own player and how long they last
+fn update_effects_text(
+    mut query: Query<&mut Text, With<EffectsText>>,
+    backend_state: Res<BackendState>,
+    connection_info: Res<ConnectionInfo>,
+) {
+    let Some(own_player) = backend_state
+        .players
+        .iter()
+        .find(|p| p.id == connection_info.uuid)
+    else {
+        return;
+    };
+
+    let effects = own_player
+        .active_effects
+        .iter()
+        .map(|effect| {
+            format!(
+                "{}: {:.1}s",
+                effect_name(effect.kind),
+                effect.ticks_remaining as f32 / backend_state.settings.ticks_per_second
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    for mut text in &mut query {
+        **text = effects.clone();
+    }
+}
+
+fn effect_name(kind: EffectKind) -> &'static str {
+    match kind {
+        EffectKind::SpeedBoost => "Speed boost",
+        EffectKind::Slowed => "Slowed",
+        EffectKind::ThinTrail => "Thin trail",
+        EffectKind::Invincible => "Invincible",
     }
 }
 
-fn setup_finished(mut commands: Commands) {
+fn setup_finished(mut commands: Commands, backend_state: Res<BackendState>) {
     commands.insert_resource(EndTimer {
-        timer: Timer::from_seconds(3.0, TimerMode::Once),
+        timer: Timer::from_seconds(5.0, TimerMode::Once),
     });
 
+    // the backend sends the scores ordered - the first one won the match
+    let winner = backend_state
+        .scores
+        .first()
+        .and_then(|score| {
+            backend_state
+                .players
+                .iter()
+                .find(|p| p.id == score.player_id)
+        })
+        .map(|player| format!(" {} wins!", player.display_name))
+        .unwrap_or_default();
+
     commands
         .spawn((
             Node {
@@ -202,6 +439,7 @@ fn setup_finished(mut commands: Commands) {
                 ..default()
             },
             OnGameScreen,
+            OnFinished,
         ))
         .with_children(|parent| {
             parent
@@ -214,13 +452,16 @@ fn setup_finished(mut commands: Commands) {
                     TextColor(Color::WHITE),
                 ))
                 .with_child((
-                    TextSpan::default(),
+                    TextSpan::new(winner),
                     TextFont {
                         font_size: 33.0,
                         ..default()
                     },
                     TextColor(Color::WHITE),
                 ));
+
+            spawn_placement_list(parent, &backend_state);
+            spawn_score_table(parent, &backend_state);
         });
 }
 
@@ -234,8 +475,11 @@ fn check_quit(
 ) {
     if end_timer.timer.tick(time.delta()).finished() {
         backend_state.countdown = 0;
-        rendered_trails.count = 0;
+        rendered_trails.trails.clear();
         backend_state.players = Vec::new();
+        backend_state.power_ups = Vec::new();
+        backend_state.scores = Vec::new();
+        backend_state.round_results = None;
         lobby_state.set(FrontendLobbyState::Loading);
         game_state.set(GameState::Menu);
     }
@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+use shared::models::player_states::DeathCause;
+
+use crate::{player::player_color, BackendState};
+
+#[derive(Component)]
+pub struct OnScoreboard;
+
+#[derive(Component)]
+pub struct NextRoundText;
+
+/// Shows the points of all players between two rounds
+pub fn setup_scoreboard(mut commands: Commands, backend_state: Res<BackendState>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            OnScoreboard,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(format!(
+                    "Round {} of {} finished",
+                    backend_state.round, backend_state.rounds_per_match
+                )),
+                TextFont {
+                    font_size: 33.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            spawn_placement_list(parent, &backend_state);
+            spawn_score_table(parent, &backend_state);
+
+            parent
+                .spawn((
+                    Text::new("Next round in: "),
+                    TextFont {
+                        font_size: 33.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ))
+                .with_child((
+                    TextSpan::default(),
+                    TextFont {
+                        font_size: 33.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    NextRoundText,
+                ));
+        });
+}
+
+pub fn update_next_round_text(
+    mut query: Query<&mut TextSpan, With<NextRoundText>>,
+    backend_state: Res<BackendState>,
+) {
+    for mut span in &mut query {
+        **span = format!("{}", backend_state.countdown);
+    }
+}
+
+/// Who survived the last round the longest and what the others crashed into
+pub fn spawn_placement_list(parent: &mut ChildBuilder, backend_state: &BackendState) {
+    let Some(round_results) = &backend_state.round_results else {
+        return;
+    };
+
+    let display_name = |player_id: &str| {
+        backend_state
+            .players
+            .iter()
+            .find(|p| p.id == player_id)
+            .map(|p| p.display_name.clone())
+            .unwrap_or_else(|| "someone who left".to_string())
+    };
+
+    parent
+        .spawn(Node {
+            margin: UiRect::vertical(Val::Px(20.0)),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+        })
+        .with_children(|parent| {
+            for placement in round_results.placements.iter() {
+                let Some(player) = backend_state
+                    .players
+                    .iter()
+                    .find(|p| p.id == placement.player_id)
+                else {
+                    continue;
+                };
+
+                let outcome = match &placement.death {
+                    None => "survived".to_string(),
+                    Some(death) => {
+                        let cause = match &death.cause {
+                            DeathCause::OwnTrail => "crashed into the own trail".to_string(),
+                            DeathCause::PlayerTrail(player_id) => {
+                                format!("crashed into the trail of {}", display_name(player_id))
+                            }
+                            DeathCause::Wall => "hit the wall".to_string(),
+                        };
+                        let seconds = death.tick.saturating_sub(round_results.started_at_tick)
+                            as f32
+                            / backend_state.settings.ticks_per_second;
+                        format!("{} after {:.1}s", cause, seconds)
+                    }
+                };
+
+                parent.spawn((
+                    Text::new(format!(
+                        "{}. {} {}",
+                        placement.place, player.display_name, outcome
+                    )),
+                    TextFont {
+                        font_size: 22.0,
+                        ..default()
+                    },
+                    TextColor(player_color(player.color)),
+                ));
+            }
+        });
+}
+
+/// One line per player with his points - the backend sends them ordered, the best player comes first
+pub fn spawn_score_table(parent: &mut ChildBuilder, backend_state: &BackendState) {
+    parent
+        .spawn(Node {
+            margin: UiRect::vertical(Val::Px(20.0)),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+        })
+        .with_children(|parent| {
+            for (place, score) in backend_state.scores.iter().enumerate() {
+                let Some(player) = backend_state
+                    .players
+                    .iter()
+                    .find(|p| p.id == score.player_id)
+                else {
+                    continue;
+                };
+
+                parent.spawn((
+                    Text::new(format!(
+                        "{}. {}: {}",
+                        place + 1,
+                        player.display_name,
+                        score.points
+                    )),
+                    TextFont {
+                        font_size: 28.0,
+                        ..default()
+                    },
+                    TextColor(player_color(player.color)),
+                ));
+            }
+        });
+}
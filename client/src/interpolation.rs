@@ -0,0 +1,133 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    f32::consts::PI,
+};
+
+use bevy::prelude::*;
+
+use crate::BackendState;
+
+// other players are rendered this far in the past, so there is nearly always a newer game-state to move towards
+const INTERPOLATION_DELAY_SECS: f64 = 0.1;
+const MILLIS_PER_SEC: f64 = 1000.0;
+
+/// Position and direction of a player inside a game-state
+struct SnapshotPlayer {
+    position: Vec2,
+    direction: f32,
+}
+
+/// Player positions of a game-state together with the server-time of its tick
+struct Snapshot {
+    server_time: f64,
+    players: HashMap<String, SnapshotPlayer>,
+}
+
+/// Buffers the last game-states of the backend, so other players can be interpolated between them.
+/// Snapshots are ordered by the server-time of their tick, so jitter on the network does not distort the movement.
+#[derive(Resource, Default)]
+pub struct SnapshotBuffer {
+    snapshots: VecDeque<Snapshot>,
+    clock_offset: Option<f64>, // local time minus server-time of the fastest game-state we received
+}
+
+impl SnapshotBuffer {
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.clock_offset = None;
+    }
+
+    /// The server-time other players are rendered at
+    pub fn render_time(&self, now: f64) -> f64 {
+        now - self.clock_offset.unwrap_or_default() - INTERPOLATION_DELAY_SECS
+    }
+
+    fn push(&mut self, received_at: f64, backend_state: &BackendState) {
+        let server_time = backend_state.server_time_millis as f64 / MILLIS_PER_SEC;
+
+        // the game-state that took the shortest time to us tells us best how far the server-clock is apart
+        let clock_offset = received_at - server_time;
+        if self.clock_offset.is_none_or(|offset| clock_offset < offset) {
+            self.clock_offset = Some(clock_offset);
+        }
+
+        let players = backend_state
+            .players
+            .iter()
+            .map(|p| {
+                (
+                    p.id.clone(),
+                    SnapshotPlayer {
+                        position: Vec2::new(p.position_x, p.position_y),
+                        direction: p.direction,
+                    },
+                )
+            })
+            .collect();
+
+        self.snapshots.push_back(Snapshot {
+            server_time,
+            players,
+        });
+    }
+
+    /// Drops all snapshots that are not needed anymore for interpolating at the given time
+    fn discard_older_than(&mut self, render_time: f64) {
+        while self.snapshots.len() > 2 && self.snapshots[1].server_time <= render_time {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Interpolated position and direction of the player at the given time
+    pub fn sample(&self, player_id: &str, render_time: f64) -> Option<(Vec2, f32)> {
+        let newer_index = self
+            .snapshots
+            .iter()
+            .position(|snapshot| snapshot.server_time > render_time);
+
+        let (older, newer) = match newer_index {
+            // we are older than all snapshots - hold the oldest one
+            Some(0) => (self.snapshots.front()?, self.snapshots.front()?),
+            Some(index) => (&self.snapshots[index - 1], &self.snapshots[index]),
+            // no newer snapshot arrived in time - hold the newest one
+            None => (self.snapshots.back()?, self.snapshots.back()?),
+        };
+
+        match (older.players.get(player_id), newer.players.get(player_id)) {
+            (Some(from), Some(to)) => {
+                let duration = newer.server_time - older.server_time;
+                let factor = if duration > 0.0 {
+                    ((render_time - older.server_time) / duration).clamp(0.0, 1.0) as f32
+                } else {
+                    1.0
+                };
+
+                Some((
+                    from.position.lerp(to.position, factor),
+                    interpolate_angle(from.direction, to.direction, factor),
+                ))
+            }
+            (Some(only), None) | (None, Some(only)) => Some((only.position, only.direction)),
+            (None, None) => None,
+        }
+    }
+}
+
+// takes the shorter way around the circle
+fn interpolate_angle(from: f32, to: f32, factor: f32) -> f32 {
+    let difference = (to - from + PI).rem_euclid(2.0 * PI) - PI;
+    from + difference * factor
+}
+
+/// Remembers every game-state of the backend when it arrives
+pub fn buffer_snapshot(
+    backend_state: Res<BackendState>,
+    mut snapshot_buffer: ResMut<SnapshotBuffer>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs_f64();
+    snapshot_buffer.push(now, &backend_state);
+
+    let render_time = snapshot_buffer.render_time(now);
+    snapshot_buffer.discard_older_than(render_time);
+}
@@ -1,30 +1,57 @@
 use bevy::prelude::*;
 use game::FrontendLobbyState;
-use networking::{setup_network_client, NetworkClient, UnboundedReceiverResource};
+use menu::{AvailableLobbies, LobbyStatus};
+use networking::{NetworkClient, NetworkEvent, NetworkUpdate, UnboundedReceiverResource};
 use player::ConnectionInfo;
-use shared::models::{network_message::NetworkMessage, player_states::PlayerStates};
+use replay::{IncomingReplay, ReplayPlayback};
+use shared::models::{
+    game_settings::GameSettings,
+    network_message::{
+        GameStateMessage, NetworkMessage, PlayerScore, RoundResults, StateAckMessage,
+    },
+    player_states::{LobbyState, PlayerStates},
+    power_up::PowerUp,
+    simulation::SimulationSync,
+    wire_format::WireFormat,
+};
 
-// this could be implemented in a way that the user can select its own server
-const BACKEND_WEBSOCKET_URL: &str = "ws://localhost";
+// binary frames are much smaller - switch to JSON if you want to read the traffic
+const BACKEND_WIRE_FORMAT: WireFormat = WireFormat::MessagePack;
 
+mod connect;
+mod connection_lost;
 mod game;
+mod interpolation;
 mod menu;
 mod networking;
 mod player;
+mod replay;
 mod splash;
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 enum GameState {
     #[default]
     Splash,
+    Connect, // the player picks the server
     Menu,
     Game,
+    ConnectionLost, // we wait for the backend to come back
 }
 
 #[derive(Resource)]
 pub struct BackendState {
     pub countdown: u32,
-    pub players: Vec<PlayerStates>,
+    pub players: Vec<PlayerStates>, // trails are put together from the trail updates of the backend
+    pub awaiting_keyframe: bool,    // we missed trail points and asked the backend for a resync
+    pub tick: u64,                  // tick of the newest game-state we applied
+    pub server_time_millis: u64,    // server-time of that tick (millis since unix-epoch)
+    pub power_ups: Vec<PowerUp>,    // power-ups lying on the board
+    pub round: u32,
+    pub rounds_per_match: u32,
+    pub scores: Vec<PlayerScore>, // the best player comes first
+    pub round_results: Option<RoundResults>, // only known when a round is over
+    pub settings: GameSettings,   // rules of our lobby - or of the replay we are watching
+    pub simulation: SimulationSync, // what we need besides the board to step the simulation ahead
 }
 
 fn main() {
@@ -37,18 +64,48 @@ fn main() {
             }),
             ..default()
         }))
-        .insert_resource(Time::<Fixed>::from_seconds(0.01))
+        // our own player is predicted with the tick-rate of the backend
+        .insert_resource(Time::<Fixed>::from_duration(
+            GameSettings::default().tick_duration(),
+        ))
         .insert_resource(BackendState {
             countdown: 0,
             players: Vec::new(),
+            awaiting_keyframe: false,
+            tick: 0,
+            server_time_millis: 0,
+            power_ups: Vec::new(),
+            round: 0,
+            rounds_per_match: 0,
+            scores: Vec::new(),
+            round_results: None,
+            settings: GameSettings::default(),
+            simulation: SimulationSync::default(),
         })
         .init_state::<GameState>()
-        .add_systems(Startup, (setup_camera, setup_network_client))
+        .add_event::<NetworkEvent>()
+        .add_systems(Startup, setup_camera)
         .add_systems(
             Update,
-            (handle_websocket_messages, check_exit_game, handle_exit),
+            (
+                // there is no network-client until the player picked a server
+                (handle_websocket_messages, handle_exit).run_if(
+                    resource_exists::<NetworkClient>
+                        .and(resource_exists::<UnboundedReceiverResource>),
+                ),
+                log_network_events,
+                sync_tick_rate,
+                check_exit_game,
+            ),
         )
-        .add_plugins((splash::splash_plugin, menu::menu_plugin, game::game_plugin))
+        .add_plugins((
+            splash::splash_plugin,
+            connect::connect_plugin,
+            connection_lost::connection_lost_plugin,
+            menu::menu_plugin,
+            game::game_plugin,
+            replay::replay_plugin,
+        ))
         .run();
 }
 
@@ -56,6 +113,28 @@ fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2d::default());
 }
 
+/// Our prediction has to run with the tick-rate of the lobby
+fn sync_tick_rate(backend_state: Res<BackendState>, mut fixed_time: ResMut<Time<Fixed>>) {
+    let tick_duration = backend_state.settings.tick_duration();
+    if fixed_time.timestep() != tick_duration {
+        fixed_time.set_timestep(tick_duration);
+    }
+}
+
+fn log_network_events(mut network_events: EventReader<NetworkEvent>) {
+    for network_event in network_events.read() {
+        match network_event {
+            NetworkEvent::Connected => println!("Connected to the backend"),
+            NetworkEvent::Disconnected(reason) => {
+                println!("Disconnected from the backend: {}", reason)
+            }
+            NetworkEvent::ProtocolError(err) => {
+                println!("Could not understand a message of the backend: {}", err)
+            }
+        }
+    }
+}
+
 /// Here we check if the user presses ESC for closing the game
 fn check_exit_game(keys: Res<ButtonInput<KeyCode>>, mut app_exit_writer: EventWriter<AppExit>) {
     // check if we pressed ESC
@@ -77,42 +156,113 @@ fn handle_exit(
     }
 }
 
+#[allow(clippy::too_many_arguments)] // bevy hands every resource in as its own parameter
 fn handle_websocket_messages(
     mut commands: Commands,
     mut message_receiver: ResMut<UnboundedReceiverResource>,
     mut lobby_state: ResMut<NextState<FrontendLobbyState>>,
     mut backend_state: ResMut<BackendState>,
+    mut available_lobbies: ResMut<AvailableLobbies>,
+    mut lobby_status: ResMut<LobbyStatus>,
     mut game_state: ResMut<NextState<GameState>>,
+    network_client: Res<NetworkClient>,
+    connection_info: Option<Res<ConnectionInfo>>,
+    mut network_events: EventWriter<NetworkEvent>,
+    mut incoming_replay: ResMut<IncomingReplay>,
+    replay_playback: Option<Res<ReplayPlayback>>,
 ) {
     if !message_receiver.receiver.is_empty() {
-        let message = message_receiver.receiver.blocking_recv();
+        let message = match message_receiver.receiver.blocking_recv() {
+            Some(NetworkUpdate::Message(message)) => Some(message),
+            Some(NetworkUpdate::Event(network_event)) => {
+                // the connection itself is handled by the screens
+                network_events.send(network_event);
+                None
+            }
+            None => None,
+        };
         if let Some(message) = message {
             match message {
                 NetworkMessage::ConnectionInfo(connection_info_message) => {
+                    // ticks are counted per lobby - start over if we are in another one now
+                    let is_other_lobby = connection_info.is_none_or(|connection_info| {
+                        connection_info.lobby_id != connection_info_message.lobby_id
+                    });
+                    // we need the token for getting our player back after losing the connection
+                    network_client.set_resume_token(connection_info_message.resume_token.clone());
+
+                    // our slot in the lobby is gone (e.g. we came back too late) - back to the menu
+                    if is_other_lobby && connection_info_message.lobby_id.is_none() {
+                        lobby_state.set(FrontendLobbyState::Loading);
+                        game_state.set(GameState::Menu);
+                    }
+
+                    if is_other_lobby {
+                        backend_state.tick = 0;
+                        backend_state.server_time_millis = 0;
+                        *lobby_status = LobbyStatus::default();
+                    }
+
                     commands.insert_resource(ConnectionInfo {
                         uuid: connection_info_message.player_id,
                         players_connected: connection_info_message.players_connected,
+                        lobby_id: connection_info_message.lobby_id,
+                        display_name: connection_info_message.display_name,
+                        color: connection_info_message.color,
+                        is_spectator: connection_info_message.is_spectator,
                     });
                 }
+                NetworkMessage::LobbyList(lobby_list_message) => {
+                    available_lobbies.lobbies = lobby_list_message.lobbies;
+                }
+                NetworkMessage::LobbyStatus(lobby_status_message) => {
+                    lobby_status.host_id = lobby_status_message.host_id;
+                    lobby_status.members = lobby_status_message.members;
+                    lobby_status.spectators = lobby_status_message.spectators;
+                    lobby_status.settings = lobby_status_message.settings;
+                    lobby_status.rejection = None;
+                    backend_state.settings = lobby_status_message.settings;
+                }
+                NetworkMessage::RequestRejected(request_rejected_message) => {
+                    lobby_status.rejection = Some(request_rejected_message.reason);
+                }
                 NetworkMessage::GameState(game_state_message) => {
+                    // the replay fills the backend-state on its own - live game-states would get mixed into it.
+                    // they are not acknowledged, so after the replay we get the trails we missed
+                    if replay_playback.is_some() {
+                        return;
+                    }
+
                     // check lobby-state
                     game_state.set(GameState::Game);
 
-                    backend_state.players = game_state_message.player_states;
-
-                    match game_state_message.lobby_state {
-                        shared::models::player_states::LobbyState::Waiting => {}
-                        shared::models::player_states::LobbyState::Countdown(num) => {
-                            backend_state.countdown = num;
-                            lobby_state.set(FrontendLobbyState::Countdown);
-                        }
-                        shared::models::player_states::LobbyState::Running => {
-                            lobby_state.set(FrontendLobbyState::Running)
-                        }
-                        shared::models::player_states::LobbyState::Finished => {
-                            lobby_state.set(FrontendLobbyState::Finished)
-                        }
-                    };
+                    let tick = game_state_message.tick;
+                    let backend_lobby_state = game_state_message.lobby_state.clone();
+
+                    // game-states that are not newer than the one we have are dropped
+                    if tick <= backend_state.tick {
+                        println!(
+                            "Dropped game-state of tick {} (already at tick {})",
+                            tick, backend_state.tick
+                        );
+                        return;
+                    }
+
+                    if apply_game_state(&mut backend_state, game_state_message) {
+                        network_client
+                            .send_message(NetworkMessage::StateAck(StateAckMessage { tick }));
+                    } else if !backend_state.awaiting_keyframe {
+                        // we are missing trail points - only a keyframe can help us now
+                        println!("Missing trail points at tick {}, requesting resync", tick);
+                        backend_state.awaiting_keyframe = true;
+                        network_client.send_message(NetworkMessage::RequestResync(()));
+                    }
+
+                    show_lobby_state(&mut backend_state, &mut lobby_state, backend_lobby_state);
+                }
+                NetworkMessage::ReplayChunk(replay_chunk) => {
+                    // the replay viewer in the menu plays the newest one
+                    incoming_replay.receive(replay_chunk);
                 }
                 _ => {}
             };
@@ -120,6 +270,74 @@ fn handle_websocket_messages(
     }
 }
 
+/// Switches to the screen that belongs to the lobby-state of the backend
+fn show_lobby_state(
+    backend_state: &mut BackendState,
+    lobby_state: &mut NextState<FrontendLobbyState>,
+    backend_lobby_state: LobbyState,
+) {
+    match backend_lobby_state {
+        LobbyState::Waiting => {}
+        LobbyState::Countdown(num) => {
+            backend_state.countdown = num;
+            lobby_state.set(FrontendLobbyState::Countdown);
+        }
+        LobbyState::Running => lobby_state.set(FrontendLobbyState::Running),
+        LobbyState::RoundOver(num) => {
+            backend_state.countdown = num;
+            lobby_state.set(FrontendLobbyState::RoundOver);
+        }
+        LobbyState::Finished => lobby_state.set(FrontendLobbyState::Finished),
+    };
+}
+
+/// Takes over the player-states of the backend and appends the trail updates to the trails we already know.
+/// Returns false if an update does not fit to our trails - then we have to wait for a keyframe.
+fn apply_game_state(backend_state: &mut BackendState, message: GameStateMessage) -> bool {
+    if backend_state.awaiting_keyframe && !message.is_keyframe {
+        return false;
+    }
+    backend_state.awaiting_keyframe = false;
+    backend_state.tick = message.tick;
+    backend_state.server_time_millis = message.server_time_millis;
+    backend_state.power_ups = message.power_ups;
+    backend_state.round = message.round;
+    backend_state.rounds_per_match = message.rounds_per_match;
+    backend_state.scores = message.scores;
+    backend_state.round_results = message.round_results;
+    backend_state.simulation = message.simulation;
+
+    let mut known_players = std::mem::take(&mut backend_state.players);
+    let mut is_complete = true;
+
+    for mut player in message.player_states {
+        // keep the trail we already put together for this player
+        if !message.is_keyframe {
+            if let Some(known_player) = known_players.iter_mut().find(|p| p.id == player.id) {
+                player.trail = std::mem::take(&mut known_player.trail);
+                player.trail_grid = std::mem::take(&mut known_player.trail_grid);
+            }
+        }
+
+        if let Some(update) = message
+            .trail_updates
+            .iter()
+            .find(|update| update.player_id == player.id)
+        {
+            let start_index = update.start_index as usize;
+            if start_index > player.trail.len() {
+                is_complete = false;
+            } else {
+                player.update_trail(start_index, &update.points, &backend_state.settings);
+            }
+        }
+
+        backend_state.players.push(player);
+    }
+
+    is_complete
+}
+
 // used for despaning all entities with a specific component
 fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
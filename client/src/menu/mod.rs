@@ -1,17 +1,61 @@
-use bevy::{app::AppExit, prelude::*};
-use shared::models::network_message::NetworkMessage;
+use bevy::{
+    app::AppExit,
+    input::keyboard::{Key, KeyboardInput},
+    prelude::*,
+};
+use shared::models::{
+    display_name::MAX_DISPLAY_NAME_LENGTH,
+    game_settings::GameSettings,
+    network_message::{
+        AddBotMessage, BotDifficulty, CreateLobbyMessage, HelloMessage, JoinLobbyMessage,
+        LobbyInfo, LobbyMember, NetworkMessage,
+    },
+};
 
-use crate::{networking::NetworkClient, player::ConnectionInfo, GameState};
+use crate::{
+    networking::NetworkClient,
+    player::{player_color, ConnectionInfo},
+    replay::start_newest_replay,
+    BackendState, GameState,
+};
 
 use super::despawn_screen;
 
 pub fn menu_plugin(app: &mut App) {
     app
         // Systems to handle the main menu screen
+        .insert_resource(AvailableLobbies {
+            lobbies: Vec::new(),
+        })
+        .insert_resource(LobbyRefreshTimer {
+            timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+        })
+        .init_resource::<DisplayNameInput>()
+        .init_resource::<LobbyStatus>()
         .add_systems(OnEnter(GameState::Menu), main_menu_setup)
         .add_systems(
             Update,
-            (update_connections_text, menu_action).run_if(in_state(GameState::Menu)),
+            (
+                update_connections_text,
+                update_lobby_text,
+                refresh_lobby_list,
+                menu_action,
+                edit_display_name,
+                update_display_name_text,
+            )
+                .run_if(in_state(GameState::Menu)),
+        )
+        .add_systems(
+            Update,
+            update_lobby_list
+                .run_if(in_state(GameState::Menu))
+                .run_if(resource_changed::<AvailableLobbies>),
+        )
+        .add_systems(
+            Update,
+            update_lobby_members
+                .run_if(in_state(GameState::Menu))
+                .run_if(resource_changed::<LobbyStatus>.or(resource_changed::<ConnectionInfo>)),
         )
         .add_systems(OnExit(GameState::Menu), despawn_screen::<OnMainMenuScreen>);
 }
@@ -22,15 +66,128 @@ struct OnMainMenuScreen;
 #[derive(Component)]
 struct ConnectionsText;
 
+#[derive(Component)]
+struct LobbyText;
+
+#[derive(Component)]
+struct LobbyListContainer;
+
+#[derive(Component)]
+struct DisplayNameText;
+
+#[derive(Component)]
+struct LobbyMembersContainer;
+
+/// Players inside our lobby and whether they are ready - the backend sends it whenever it changes
+#[derive(Resource, Default)]
+pub struct LobbyStatus {
+    pub host_id: Option<String>,
+    pub members: Vec<LobbyMember>,
+    pub spectators: Vec<String>, // names of everyone who is only watching
+    pub settings: GameSettings, // rules the next match is played with - only the host can change them
+    pub rejection: Option<String>, // why the backend did not start the match
+}
+
+/// Name the player is typing in - it is sent to the server when pressing enter
+#[derive(Resource, Default)]
+struct DisplayNameInput {
+    name: String,
+    is_editing: bool, // as long as the player is typing, the name of the server is not taken over
+}
+
+/// Lobbies that are currently open on the server
+#[derive(Resource)]
+pub struct AvailableLobbies {
+    pub lobbies: Vec<LobbyInfo>,
+}
+
+/// We ask the server periodically for the open lobbies while being in the menu
+#[derive(Resource)]
+struct LobbyRefreshTimer {
+    timer: Timer,
+}
+
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const LOBBY_BUTTON: Color = Color::srgb(0.2, 0.2, 0.3);
+
+/// Value of the game-settings that can be changed with the buttons next to it
+#[derive(Clone, Copy)]
+enum SettingField {
+    BoardWidth,
+    BoardHeight,
+    MoveSpeed,
+    RotationSpeed,
+    TickRate,
+    CollisionRadius,
+    UnspawnableEdge,
+}
+
+impl SettingField {
+    const ALL: [SettingField; 7] = [
+        SettingField::BoardWidth,
+        SettingField::BoardHeight,
+        SettingField::MoveSpeed,
+        SettingField::RotationSpeed,
+        SettingField::TickRate,
+        SettingField::CollisionRadius,
+        SettingField::UnspawnableEdge,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SettingField::BoardWidth => "Board width",
+            SettingField::BoardHeight => "Board height",
+            SettingField::MoveSpeed => "Move speed",
+            SettingField::RotationSpeed => "Rotation speed",
+            SettingField::TickRate => "Tick rate",
+            SettingField::CollisionRadius => "Collision radius",
+            SettingField::UnspawnableEdge => "Unspawnable edge",
+        }
+    }
+
+    // how much a single click changes the value
+    fn step(&self) -> f32 {
+        match self {
+            SettingField::BoardWidth | SettingField::BoardHeight => 100.0,
+            SettingField::MoveSpeed | SettingField::UnspawnableEdge => 25.0,
+            SettingField::RotationSpeed => 0.25,
+            SettingField::TickRate => 10.0,
+            SettingField::CollisionRadius => 2.0,
+        }
+    }
+
+    fn value_mut<'a>(&self, settings: &'a mut GameSettings) -> &'a mut f32 {
+        match self {
+            SettingField::BoardWidth => &mut settings.board_width,
+            SettingField::BoardHeight => &mut settings.board_height,
+            SettingField::MoveSpeed => &mut settings.move_speed,
+            SettingField::RotationSpeed => &mut settings.rotation_speed,
+            SettingField::TickRate => &mut settings.ticks_per_second,
+            SettingField::CollisionRadius => &mut settings.collision_radius,
+            SettingField::UnspawnableEdge => &mut settings.unspawnable_edge,
+        }
+    }
+}
 
 #[derive(Component)]
 enum MenuButtonAction {
     Play,
+    ToggleReady,
+    AddBot(BotDifficulty),
+    ChangeSetting(SettingField, f32), // the value is changed by this many steps
+    CreateLobby,
+    JoinLobby(String),
+    SpectateLobby(String),
+    LeaveLobby,
+    ChangeColor,
+    WatchReplay,
     Quit,
 }
 
-fn main_menu_setup(mut commands: Commands) {
+fn main_menu_setup(mut commands: Commands, network_client: Res<NetworkClient>) {
+    // fetch the open lobbies right away instead of waiting for the first refresh
+    network_client.send_message(NetworkMessage::ListLobbies(()));
+
     let button_node = Node {
         width: Val::Px(300.0),
         height: Val::Px(65.0),
@@ -69,7 +226,43 @@ fn main_menu_setup(mut commands: Commands) {
                 TextColor(Color::WHITE),
             ));
 
-            // play button
+            // name of the player - can be changed by typing
+            parent
+                .spawn((
+                    Text::new("Name: "),
+                    TextFont {
+                        font_size: 33.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ))
+                .with_child((
+                    TextSpan::default(),
+                    TextFont {
+                        font_size: 33.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    DisplayNameText,
+                ));
+
+            // ready button - the match starts when everyone in the lobby is ready
+            parent
+                .spawn((
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(NORMAL_BUTTON),
+                    MenuButtonAction::ToggleReady,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Ready"),
+                        button_text_font.clone(),
+                        TextColor(Color::WHITE),
+                    ));
+                });
+
+            // play button - only the host can start without everyone being ready
             parent
                 .spawn((
                     Button,
@@ -79,24 +272,128 @@ fn main_menu_setup(mut commands: Commands) {
                 ))
                 .with_children(|parent| {
                     parent.spawn((
-                        Text::new("New Game"),
+                        Text::new("Force Start"),
+                        button_text_font.clone(),
+                        TextColor(Color::WHITE),
+                    ));
+                });
+
+            // the host can fill up the lobby with bots
+            parent.spawn(Node::default()).with_children(|parent| {
+                for (difficulty, label) in [
+                    (BotDifficulty::Easy, "Easy Bot"),
+                    (BotDifficulty::Hard, "Hard Bot"),
+                ] {
+                    parent
+                        .spawn((
+                            Button,
+                            Node {
+                                width: Val::Px(130.0),
+                                ..button_node.clone()
+                            },
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::AddBot(difficulty),
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                Text::new(label),
+                                TextFont {
+                                    font_size: 26.0,
+                                    ..default()
+                                },
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+                }
+            });
+
+            // create lobby button
+            parent
+                .spawn((
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(NORMAL_BUTTON),
+                    MenuButtonAction::CreateLobby,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Create Lobby"),
                         button_text_font.clone(),
                         TextColor(Color::WHITE),
                     ));
                 });
 
-            // quit button
+            // leave lobby button
             parent
                 .spawn((
                     Button,
-                    button_node,
+                    button_node.clone(),
                     BackgroundColor(NORMAL_BUTTON),
-                    MenuButtonAction::Quit,
+                    MenuButtonAction::LeaveLobby,
                 ))
                 .with_children(|parent| {
-                    parent.spawn((Text::new("Quit"), button_text_font, TextColor(Color::WHITE)));
+                    parent.spawn((
+                        Text::new("Leave Lobby"),
+                        button_text_font.clone(),
+                        TextColor(Color::WHITE),
+                    ));
                 });
 
+            // change color button
+            parent
+                .spawn((
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(NORMAL_BUTTON),
+                    MenuButtonAction::ChangeColor,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("Change Color"),
+                        button_text_font.clone(),
+                        TextColor(Color::WHITE),
+                    ));
+                });
+
+            // replay and quit button
+            parent.spawn(Node::default()).with_children(|parent| {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(130.0),
+                            ..button_node.clone()
+                        },
+                        BackgroundColor(NORMAL_BUTTON),
+                        MenuButtonAction::WatchReplay,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Text::new("Replay"),
+                            button_text_font.clone(),
+                            TextColor(Color::WHITE),
+                        ));
+                    });
+
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Px(130.0),
+                            ..button_node
+                        },
+                        BackgroundColor(NORMAL_BUTTON),
+                        MenuButtonAction::Quit,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Text::new("Quit"),
+                            button_text_font,
+                            TextColor(Color::WHITE),
+                        ));
+                    });
+            });
+
             // count of players connected text
             parent
                 .spawn((
@@ -116,16 +413,63 @@ fn main_menu_setup(mut commands: Commands) {
                     TextColor(Color::WHITE),
                     ConnectionsText,
                 ));
+
+            // lobby the player is currently in
+            parent
+                .spawn((
+                    Text::new("Lobby: "),
+                    TextFont {
+                        font_size: 33.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ))
+                .with_child((
+                    TextSpan::default(),
+                    TextFont {
+                        font_size: 33.0,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    LobbyText,
+                ));
+
+            // players inside our lobby - gets filled by update_lobby_members
+            parent.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                LobbyMembersContainer,
+            ));
+
+            // list of open lobbies - gets filled by update_lobby_list
+            parent.spawn((
+                Node {
+                    margin: UiRect::top(Val::Px(20.0)),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                LobbyListContainer,
+            ));
         });
 }
 
+#[allow(clippy::too_many_arguments)] // bevy hands every resource in as its own parameter
 fn menu_action(
+    mut commands: Commands,
     interaction_query: Query<
         (&Interaction, &MenuButtonAction),
         (Changed<Interaction>, With<Button>),
     >,
     mut app_exit_events: EventWriter<AppExit>,
     network_client: Res<NetworkClient>,
+    connection_info: Res<ConnectionInfo>,
+    mut backend_state: ResMut<BackendState>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut lobby_status: ResMut<LobbyStatus>,
 ) {
     for (interaction, menu_button_action) in &interaction_query {
         if *interaction == Interaction::Pressed {
@@ -135,6 +479,71 @@ fn menu_action(
                     network_client.send_message(NetworkMessage::RequestStart(()));
                 }
 
+                MenuButtonAction::ToggleReady => {
+                    network_client.send_message(NetworkMessage::ToggleReady(()));
+                }
+
+                MenuButtonAction::AddBot(difficulty) => {
+                    network_client.send_message(NetworkMessage::AddBot(AddBotMessage {
+                        difficulty: *difficulty,
+                    }));
+                }
+
+                MenuButtonAction::ChangeSetting(field, steps) => {
+                    // the backend checks the value and only lets the host change it
+                    let mut settings = lobby_status.settings;
+                    *field.value_mut(&mut settings) += field.step() * steps;
+                    network_client.send_message(NetworkMessage::ChangeSettings(settings));
+                }
+
+                MenuButtonAction::CreateLobby => {
+                    // let the server choose a name for the lobby
+                    network_client.send_message(NetworkMessage::CreateLobby(CreateLobbyMessage {
+                        name: String::new(),
+                    }));
+                }
+
+                MenuButtonAction::JoinLobby(lobby_id) => {
+                    network_client.send_message(NetworkMessage::JoinLobby(JoinLobbyMessage {
+                        lobby_id: lobby_id.clone(),
+                        as_spectator: false,
+                    }));
+                }
+
+                MenuButtonAction::SpectateLobby(lobby_id) => {
+                    network_client.send_message(NetworkMessage::JoinLobby(JoinLobbyMessage {
+                        lobby_id: lobby_id.clone(),
+                        as_spectator: true,
+                    }));
+                }
+
+                MenuButtonAction::LeaveLobby => {
+                    network_client.send_message(NetworkMessage::LeaveLobby(()));
+                }
+
+                MenuButtonAction::ChangeColor => {
+                    // inside a lobby the server skips forward past the colors other players already have
+                    network_client.send_message(NetworkMessage::Hello(HelloMessage {
+                        display_name: None,
+                        preferred_color: Some(connection_info.color.next()),
+                    }));
+                }
+
+                MenuButtonAction::WatchReplay => {
+                    // the game-states of the lobby would mix up with the replay
+                    if connection_info.lobby_id.is_some() {
+                        lobby_status.rejection =
+                            Some("Leave the lobby to watch a replay".to_string());
+                        continue;
+                    }
+
+                    if let Err(err) =
+                        start_newest_replay(&mut commands, &mut backend_state, &mut game_state)
+                    {
+                        println!("Could not watch replay: {}", err);
+                    }
+                }
+
                 MenuButtonAction::Quit => {
                     app_exit_events.send(AppExit::Success);
                 }
@@ -143,6 +552,70 @@ fn menu_action(
     }
 }
 
+/// Typing changes the name, enter sends it to the server
+fn edit_display_name(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut display_name_input: ResMut<DisplayNameInput>,
+    network_client: Res<NetworkClient>,
+) {
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Character(characters) => {
+                display_name_input.is_editing = true;
+                for character in characters.chars() {
+                    if display_name_input.name.chars().count() < MAX_DISPLAY_NAME_LENGTH {
+                        display_name_input.name.push(character);
+                    }
+                }
+            }
+            Key::Space => {
+                display_name_input.is_editing = true;
+                if display_name_input.name.chars().count() < MAX_DISPLAY_NAME_LENGTH {
+                    display_name_input.name.push(' ');
+                }
+            }
+            Key::Backspace => {
+                display_name_input.is_editing = true;
+                display_name_input.name.pop();
+            }
+            Key::Enter if display_name_input.is_editing => {
+                // the server checks the name and tells us which one we got
+                display_name_input.is_editing = false;
+                network_client.send_message(NetworkMessage::Hello(HelloMessage {
+                    display_name: Some(display_name_input.name.clone()),
+                    preferred_color: None,
+                }));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_display_name_text(
+    mut query: Query<(&mut TextSpan, &mut TextColor), With<DisplayNameText>>,
+    mut display_name_input: ResMut<DisplayNameInput>,
+    connection_info: Res<ConnectionInfo>,
+) {
+    if !display_name_input.is_editing && display_name_input.name != connection_info.display_name {
+        display_name_input.name = connection_info.display_name.clone();
+    }
+
+    // a cursor shows that the name was not sent yet
+    let cursor = if display_name_input.is_editing {
+        "_"
+    } else {
+        ""
+    };
+    for (mut span, mut text_color) in &mut query {
+        **span = format!("{}{}", display_name_input.name, cursor);
+        text_color.0 = player_color(connection_info.color);
+    }
+}
+
 fn update_connections_text(
     mut query: Query<&mut TextSpan, With<ConnectionsText>>,
     connection_info: Res<ConnectionInfo>,
@@ -151,3 +624,216 @@ fn update_connections_text(
         **span = format!("{}", connection_info.players_connected);
     }
 }
+
+fn update_lobby_text(
+    mut query: Query<&mut TextSpan, With<LobbyText>>,
+    connection_info: Res<ConnectionInfo>,
+    available_lobbies: Res<AvailableLobbies>,
+) {
+    let lobby_name = match &connection_info.lobby_id {
+        Some(lobby_id) => available_lobbies
+            .lobbies
+            .iter()
+            .find(|lobby| &lobby.lobby_id == lobby_id)
+            .map(|lobby| lobby.name.clone())
+            .unwrap_or_default(),
+        None => "-".to_string(),
+    };
+
+    for mut span in &mut query {
+        **span = lobby_name.clone();
+    }
+}
+
+fn refresh_lobby_list(
+    mut refresh_timer: ResMut<LobbyRefreshTimer>,
+    time: Res<Time>,
+    network_client: Res<NetworkClient>,
+) {
+    if refresh_timer.timer.tick(time.delta()).just_finished() {
+        network_client.send_message(NetworkMessage::ListLobbies(()));
+    }
+}
+
+/// Lists the players of our lobby with their ready-state, the settings of the next match and why the last start was rejected
+fn update_lobby_members(
+    mut commands: Commands,
+    container_query: Query<Entity, With<LobbyMembersContainer>>,
+    lobby_status: Res<LobbyStatus>,
+    connection_info: Res<ConnectionInfo>,
+) {
+    let text_font = TextFont {
+        font_size: 22.0,
+        ..default()
+    };
+
+    for container in &container_query {
+        commands
+            .entity(container)
+            .despawn_descendants()
+            .with_children(|parent| {
+                // outside of a lobby there is nobody to list
+                if connection_info.lobby_id.is_none() {
+                    return;
+                }
+
+                for member in lobby_status.members.iter() {
+                    let host = if lobby_status.host_id.as_ref() == Some(&member.player_id) {
+                        " (Host)"
+                    } else {
+                        ""
+                    };
+                    let ready = if member.is_bot {
+                        "bot"
+                    } else if !member.is_connected {
+                        "reconnecting"
+                    } else if member.is_ready {
+                        "ready"
+                    } else {
+                        "not ready"
+                    };
+
+                    parent.spawn((
+                        Text::new(format!("{}{} - {}", member.display_name, host, ready)),
+                        text_font.clone(),
+                        TextColor(player_color(member.color)),
+                    ));
+                }
+
+                if !lobby_status.spectators.is_empty() {
+                    parent.spawn((
+                        Text::new(format!("Watching: {}", lobby_status.spectators.join(", "))),
+                        text_font.clone(),
+                        TextColor(Color::srgb(0.7, 0.7, 0.7)),
+                    ));
+                }
+
+                spawn_settings(parent, &lobby_status.settings, &text_font);
+
+                if let Some(rejection) = &lobby_status.rejection {
+                    parent.spawn((
+                        Text::new(rejection.clone()),
+                        text_font.clone(),
+                        TextColor(Color::srgb(0.9, 0.3, 0.3)),
+                    ));
+                }
+            });
+    }
+}
+
+/// Every value of the settings with a button for lowering and one for raising it
+fn spawn_settings(parent: &mut ChildBuilder, settings: &GameSettings, text_font: &TextFont) {
+    let step_button_node = Node {
+        width: Val::Px(32.0),
+        height: Val::Px(28.0),
+        margin: UiRect::horizontal(Val::Px(4.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    for field in SettingField::ALL {
+        let mut settings = *settings;
+        let value = *field.value_mut(&mut settings);
+
+        parent
+            .spawn(Node {
+                align_items: AlignItems::Center,
+                margin: UiRect::top(Val::Px(2.0)),
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn((
+                    Text::new(format!("{}: {}", field.label(), value)),
+                    text_font.clone(),
+                    TextColor(Color::WHITE),
+                ));
+
+                for (label, steps) in [("-", -1.0), ("+", 1.0)] {
+                    parent
+                        .spawn((
+                            Button,
+                            step_button_node.clone(),
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::ChangeSetting(field, steps),
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                Text::new(label),
+                                text_font.clone(),
+                                TextColor(Color::WHITE),
+                            ));
+                        });
+                }
+            });
+    }
+}
+
+/// Rebuilds the buttons for joining or watching a lobby whenever the server sent us a new list
+fn update_lobby_list(
+    mut commands: Commands,
+    container_query: Query<Entity, With<LobbyListContainer>>,
+    available_lobbies: Res<AvailableLobbies>,
+) {
+    let lobby_button_node = Node {
+        width: Val::Px(300.0),
+        height: Val::Px(40.0),
+        margin: UiRect::all(Val::Px(5.0)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let spectate_button_node = Node {
+        width: Val::Px(90.0),
+        ..lobby_button_node.clone()
+    };
+    let text_font = TextFont {
+        font_size: 22.0,
+        ..default()
+    };
+
+    for container in &container_query {
+        commands
+            .entity(container)
+            .despawn_descendants()
+            .with_children(|parent| {
+                for lobby in available_lobbies.lobbies.iter() {
+                    parent.spawn(Node::default()).with_children(|parent| {
+                        parent
+                            .spawn((
+                                Button,
+                                lobby_button_node.clone(),
+                                BackgroundColor(LOBBY_BUTTON),
+                                MenuButtonAction::JoinLobby(lobby.lobby_id.clone()),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    Text::new(format!(
+                                        "{} ({} players)",
+                                        lobby.name, lobby.players_connected
+                                    )),
+                                    text_font.clone(),
+                                    TextColor(Color::WHITE),
+                                ));
+                            });
+
+                        // spectators only watch the rounds without playing
+                        parent
+                            .spawn((
+                                Button,
+                                spectate_button_node.clone(),
+                                BackgroundColor(LOBBY_BUTTON),
+                                MenuButtonAction::SpectateLobby(lobby.lobby_id.clone()),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    Text::new("Watch"),
+                                    text_font.clone(),
+                                    TextColor(Color::WHITE),
+                                ));
+                            });
+                    });
+                }
+            });
+    }
+}
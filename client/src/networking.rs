@@ -1,93 +1,195 @@
-use std::sync::Arc;
+use std::{
+    net::Ipv6Addr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use async_std::{net::TcpStream, sync::Mutex};
+use async_std::{net::TcpStream, sync::Mutex, task::sleep};
 use async_tungstenite::{async_std::connect_async, tungstenite::Message, WebSocketStream};
 use bevy::prelude::*;
 use bevy::tasks::AsyncComputeTaskPool;
 use futures::{stream::SplitSink, SinkExt, StreamExt};
-use shared::models::{network_message::NetworkMessage, PORT};
+use shared::models::{
+    network_message::NetworkMessage,
+    wire_format::{EncodedMessage, WireFormat},
+    PORT,
+};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 
-use crate::BACKEND_WEBSOCKET_URL;
+use crate::BACKEND_WIRE_FORMAT;
+
+// after losing the connection we wait a bit longer with every failed attempt to reconnect
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(8);
+// the backend keeps our player for 30 seconds - trying longer than that is pointless
+const MAX_RECONNECT_ATTEMPTS: u32 = 7;
+
+/// What happened to the connection - the bevy-systems decide which screen to show for it
+#[derive(Event, Clone, Debug)]
+pub enum NetworkEvent {
+    Connected,
+    Disconnected(String), // why the connection was lost or could not be established
+    ProtocolError(String), // the backend sent something we do not understand - the frame is skipped
+}
+
+/// Everything the networking task hands over to the bevy-loop - in the order it happened
+pub enum NetworkUpdate {
+    Message(NetworkMessage),
+    Event(NetworkEvent),
+}
 
 #[derive(Resource)]
 pub struct UnboundedReceiverResource {
-    pub receiver: UnboundedReceiver<NetworkMessage>,
+    pub receiver: UnboundedReceiver<NetworkUpdate>,
 }
 
 #[derive(Resource)]
 pub struct NetworkClient {
     socket_url: String,
+    wire_format: WireFormat,
     write_socket: Arc<Mutex<Option<SplitSink<WebSocketStream<TcpStream>, Message>>>>,
-    unbounded_sender: Arc<UnboundedSender<NetworkMessage>>,
+    unbounded_sender: Arc<UnboundedSender<NetworkUpdate>>,
+    resume_token: Arc<std::sync::Mutex<Option<String>>>, // sent when reconnecting to get our player back
+    is_closing: Arc<AtomicBool>, // we closed the connection on purpose or gave up - no reconnecting anymore
 }
 
 impl NetworkClient {
     /// Creating a new network-client with the given URL.
-    pub fn new(websocket_url: String, unbounded_sender: UnboundedSender<NetworkMessage>) -> Self {
+    /// The wire-format is negotiated with the backend when connecting.
+    pub fn new(
+        websocket_url: String,
+        wire_format: WireFormat,
+        unbounded_sender: UnboundedSender<NetworkUpdate>,
+    ) -> Self {
         NetworkClient {
             socket_url: websocket_url,
+            wire_format,
             write_socket: Arc::new(Mutex::new(None)),
             unbounded_sender: Arc::new(unbounded_sender),
+            resume_token: Arc::new(std::sync::Mutex::new(None)),
+            is_closing: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    /// Will connect to the websocket.
-    /// If it is not able to connect, we will panic for the sake of simplicity.
+    /// Will connect to the websocket - every connection and every loss of it is reported as network-event.
+    /// If the first attempt fails, we give up right away - the player has probably picked the wrong server.
+    /// If an established connection gets lost, we try again a few times with a growing delay.
     pub fn connect(&mut self) -> () {
         // spawn a new thread to create the websocket-connection and handle incoming messages
         // save the write-stream in the network-client
         let cloned_socket_url = self.socket_url.clone();
+        let wire_format = self.wire_format;
         let cloned_write_socket = Arc::clone(&self.write_socket);
         let cloned_sender = Arc::clone(&self.unbounded_sender);
+        let cloned_resume_token = Arc::clone(&self.resume_token);
+        let cloned_is_closing = Arc::clone(&self.is_closing);
 
         let task_pool = AsyncComputeTaskPool::get();
         task_pool
             .spawn(async move {
-                let (ws_stream, _) = connect_async(cloned_socket_url)
-                    .await
-                    .expect("Failed to connect to the Websocket!");
+                let mut reconnect_delay = INITIAL_RECONNECT_DELAY;
+                let mut failed_attempts = 0;
+                let mut was_connected = false;
 
-                let (write_stream, mut read_stream) = ws_stream.split();
+                // nobody is listening anymore once the game dropped the receiver
+                while !cloned_is_closing.load(Ordering::Relaxed) && !cloned_sender.is_closed() {
+                    // with the token of our last connection the backend gives us our player back
+                    let resume_token = cloned_resume_token
+                        .lock()
+                        .map(|token| token.clone())
+                        .unwrap_or_default();
+                    let url = format!(
+                        "{}/{}",
+                        cloned_socket_url,
+                        wire_format.to_query(resume_token.as_deref())
+                    );
 
-                // save the write-stream in the network-client and drop the lock
-                {
-                    let mut write_socket = cloned_write_socket.lock().await;
-                    *write_socket = Some(write_stream);
-                }
+                    match connect_async(url).await {
+                        Ok((ws_stream, _)) => {
+                            was_connected = true;
+                            failed_attempts = 0;
+                            reconnect_delay = INITIAL_RECONNECT_DELAY;
+                            let _ =
+                                cloned_sender.send(NetworkUpdate::Event(NetworkEvent::Connected));
+
+                            let reason =
+                                receive_messages(ws_stream, &cloned_write_socket, &cloned_sender)
+                                    .await;
+
+                            // closing the connection on purpose is nothing we have to tell anyone
+                            if cloned_is_closing.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            let _ = cloned_sender
+                                .send(NetworkUpdate::Event(NetworkEvent::Disconnected(reason)));
+                        }
+                        Err(err) => {
+                            println!("Failed to connect to the backend: {}", err);
+                            failed_attempts += 1;
 
-                // handle incoming messages and send them to the unbounded-channel
-                loop {
-                    let msg: Message = read_stream.next().await.unwrap().unwrap();
+                            if !was_connected || failed_attempts >= MAX_RECONNECT_ATTEMPTS {
+                                cloned_is_closing.store(true, Ordering::Relaxed);
+                                let _ = cloned_sender.send(NetworkUpdate::Event(
+                                    NetworkEvent::Disconnected(err.to_string()),
+                                ));
+                                break;
+                            }
+                        }
+                    }
 
-                    // let msg = read.stream.next().await.unwrap().unwrap();
-                    let deserialized: NetworkMessage =
-                        serde_json::from_str(msg.to_text().unwrap()).unwrap();
-                    cloned_sender.send(deserialized).unwrap();
+                    println!("Reconnecting in {:?}", reconnect_delay);
+                    sleep(reconnect_delay).await;
+                    reconnect_delay = (reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
                 }
             })
             .detach();
     }
 
+    /// False once we gave up reconnecting or closed the connection on purpose
+    pub fn is_reconnecting(&self) -> bool {
+        !self.is_closing.load(Ordering::Relaxed)
+    }
+
+    /// Remembers the token the backend gave us - it is used when we have to reconnect
+    pub fn set_resume_token(&self, resume_token: String) {
+        if let Ok(mut token) = self.resume_token.lock() {
+            *token = Some(resume_token);
+        }
+    }
+
     pub fn send_message(&self, message: NetworkMessage) -> () {
         // spawn an async call and don't wait on it to send the message
         let cloned_write_socket = Arc::clone(&self.write_socket);
+        let wire_format = self.wire_format;
         let task_pool = AsyncComputeTaskPool::get();
 
         task_pool
             .spawn(async move {
                 let mut write_socket_lock = cloned_write_socket.lock().await;
+                // while we are reconnecting there is no socket - the message is dropped
                 if let Some(write) = &mut *write_socket_lock {
-                    write
-                        .send(Message::text(serde_json::to_string(&message).unwrap()))
-                        .await
-                        .unwrap();
+                    let message = match message.encode(wire_format) {
+                        Ok(EncodedMessage::Text(text)) => Message::Text(text),
+                        Ok(EncodedMessage::Binary(bytes)) => Message::Binary(bytes),
+                        Err(err) => {
+                            println!("Failed to serialize message: {}", err);
+                            return;
+                        }
+                    };
+                    if let Err(err) = write.send(message).await {
+                        println!("Failed to send message: {}", err);
+                    }
                 }
             })
             .detach();
     }
 
     pub fn disconnect(&mut self) -> () {
+        self.is_closing.store(true, Ordering::Relaxed);
+
         let cloned_write_socket = Arc::clone(&self.write_socket);
         let task_pool = AsyncComputeTaskPool::get();
         task_pool
@@ -102,16 +204,130 @@ impl NetworkClient {
     }
 }
 
+/// Handles the incoming messages of the websocket until the connection is lost - returns why it was lost
+async fn receive_messages(
+    ws_stream: WebSocketStream<TcpStream>,
+    write_socket: &Mutex<Option<SplitSink<WebSocketStream<TcpStream>, Message>>>,
+    sender: &UnboundedSender<NetworkUpdate>,
+) -> String {
+    let (write_stream, mut read_stream) = ws_stream.split();
+
+    // save the write-stream in the network-client and drop the lock
+    {
+        let mut write_socket = write_socket.lock().await;
+        *write_socket = Some(write_stream);
+    }
+
+    // handle incoming messages and send them to the unbounded-channel
+    let mut reason = "The backend closed the connection".to_string();
+    while let Some(message) = read_stream.next().await {
+        let message = match message {
+            Ok(message) => message,
+            Err(err) => {
+                println!("Lost connection to the backend: {}", err);
+                reason = err.to_string();
+                break;
+            }
+        };
+
+        // the backend answers in the format we requested, but we understand both
+        let deserialized = match message {
+            Message::Text(text) => NetworkMessage::decode_text(&text),
+            Message::Binary(bytes) => NetworkMessage::decode_binary(&bytes),
+            _ => continue,
+        };
+
+        let update = match deserialized {
+            Ok(deserialized) => NetworkUpdate::Message(deserialized),
+            Err(err) => NetworkUpdate::Event(NetworkEvent::ProtocolError(err.to_string())),
+        };
+        if sender.send(update).is_err() {
+            // nobody is listening anymore - the game was closed
+            reason = "The game stopped listening".to_string();
+            break;
+        }
+    }
+
+    // messages are dropped until we are connected again
+    *write_socket.lock().await = None;
+    reason
+}
+
+/// Turns the address the player typed in into a websocket-url - scheme and port can be left out.
+/// `localhost` becomes `ws://localhost:11255`, `[::1]:8080` becomes `ws://[::1]:8080`.
+pub fn websocket_url(address: &str) -> Result<String, String> {
+    let address = address.trim().trim_end_matches('/');
+    if address.is_empty() {
+        return Err("Please enter the address of a server".to_string());
+    }
+    if address.contains(char::is_whitespace) {
+        return Err(format!("{} is not a valid address", address));
+    }
+
+    // the client is built without tls - a secure connection would fail anyway
+    let host = match address.split_once("://") {
+        Some(("ws", host)) => host,
+        Some(("wss", _)) => {
+            return Err("Secure connections (wss) are not supported, use ws".to_string())
+        }
+        Some((scheme, _)) => return Err(format!("Unknown scheme {}, use ws", scheme)),
+        None => address,
+    };
+
+    // ipv6-addresses have to be written in brackets, otherwise their colons can not be told apart from the port
+    let (hostname, port) = match host.strip_prefix('[') {
+        Some(rest) => {
+            let (ip, after_ip) = rest
+                .split_once(']')
+                .ok_or_else(|| format!("{} is missing the closing bracket", address))?;
+            if ip.parse::<Ipv6Addr>().is_err() {
+                return Err(format!("{} is not a valid IPv6 address", ip));
+            }
+            let port = match after_ip {
+                "" => None,
+                _ => Some(
+                    after_ip
+                        .strip_prefix(':')
+                        .ok_or_else(|| format!("{} is not a valid address", address))?,
+                ),
+            };
+            (ip, port)
+        }
+        None if host.matches(':').count() > 1 => {
+            return Err(format!(
+                "IPv6 addresses have to be written in brackets, e.g. [{}]",
+                host
+            ))
+        }
+        None => match host.split_once(':') {
+            Some((hostname, port)) => (hostname, Some(port)),
+            None => (host, None),
+        },
+    };
+    if hostname.is_empty() {
+        return Err(format!("{} is missing the host", address));
+    }
+
+    match port {
+        Some(port) => {
+            port.parse::<u16>()
+                .map_err(|_| format!("{} is not a valid port", port))?;
+            Ok(format!("ws://{}", host))
+        }
+        None => Ok(format!("ws://{}:{}", host, PORT)),
+    }
+}
+
 /// Here we setup all the necessary stuff for properly connect to the websocket.
 /// We open up an unbounded-channel, so the network-client is able to notify the methods inside the bevy-loop about new websocket-messages from the backend.
 /// We are creating the network-client, give him the sender of the unbounded-channel and connect to the websocket.
-/// Then we are registering the client as well as the receiver of the unbounded-channel as bevy-resource.
-pub fn setup_network_client(mut commands: Commands) {
+/// Then we are registering the client as well as the receiver of the unbounded-channel as bevy-resource - a client we had before is replaced.
+pub fn setup_network_client(commands: &mut Commands, websocket_url: String) {
     // create the mpsc-channel
-    let (sender, receiver) = unbounded_channel::<NetworkMessage>();
+    let (sender, receiver) = unbounded_channel::<NetworkUpdate>();
 
     // create network-client and put it into resources
-    let mut client = NetworkClient::new(format!("{}:{}", BACKEND_WEBSOCKET_URL, PORT), sender);
+    let mut client = NetworkClient::new(websocket_url, BACKEND_WIRE_FORMAT, sender);
     client.connect();
 
     commands.insert_resource(client);
@@ -1,124 +1,307 @@
+use std::collections::{HashMap, VecDeque};
+
 use bevy::prelude::*;
 use shared::models::{
     direction::Direction,
     network_message::{NetworkMessage, PlayerUpdateMessage},
-    MOVE_SPEED, ROTATION_SPEED,
+    player_color::PlayerColor,
+    player_states::PlayerStates,
+    simulation::{simulate, PlayerInput, SimulationState},
+};
+
+use crate::{
+    game::{OnGameScreen, OnRound},
+    interpolation::SnapshotBuffer,
+    networking::NetworkClient,
+    BackendState,
 };
 
-use crate::{game::OnGameScreen, networking::NetworkClient, BackendState};
+// inputs of the last two seconds are kept for replaying them
+const PENDING_INPUT_SECONDS: f32 = 2.0;
+
+// names are shown this far above the players
+const LABEL_OFFSET: f32 = 28.0;
 
 #[derive(Resource)]
 pub struct ConnectionInfo {
     pub uuid: String,
     pub players_connected: u32,
+    pub lobby_id: Option<String>,
+    pub display_name: String,
+    pub color: PlayerColor,
+    pub is_spectator: bool, // spectators have no own player
 }
 
-#[derive(Resource)]
+/// Trail points of a single player we already spawned
+#[derive(Default)]
+pub struct RenderedTrail {
+    pub count: usize,
+    pub first_point: Option<(f32, f32)>, // a trail that starts somewhere else was replaced
+}
+
+/// Trails we already spawned - keyed by the id of the player
+#[derive(Resource, Default)]
 pub struct RenderedTrails {
-    pub count: u32,
+    pub trails: HashMap<String, RenderedTrail>,
 }
 
 #[derive(Component)]
 pub struct Player {
     pub uuid: String,
-    pub rotation: Quat,
     pub is_alive: bool,
     pub is_own_player: bool,
-    pub current_direction: Direction,
 }
 
-impl Player {
-    pub fn steer_player(
-        &mut self,
-        keys: &Res<ButtonInput<KeyCode>>,
-        time_delta: f32,
-    ) -> Option<PlayerUpdateMessage> {
-        let left_is_clicked = keys.pressed(KeyCode::ArrowLeft);
-        let right_is_clicked = keys.pressed(KeyCode::ArrowRight);
-
-        if left_is_clicked == right_is_clicked {
-            if self.current_direction != Direction::Straight {
-                self.current_direction = Direction::Straight;
-                return Some(PlayerUpdateMessage {
-                    current_direction: Direction::Straight,
-                });
-            }
-        } else if keys.pressed(KeyCode::ArrowLeft) {
-            let rotation = Quat::from_rotation_z(ROTATION_SPEED * time_delta);
-            self.rotation = rotation * self.rotation;
-
-            if self.current_direction != Direction::Left {
-                self.current_direction = Direction::Left;
-                return Some(PlayerUpdateMessage {
-                    current_direction: Direction::Left,
-                });
-            }
-        } else if keys.pressed(KeyCode::ArrowRight) {
-            let rotation = Quat::from_rotation_z(-ROTATION_SPEED * time_delta);
-            self.rotation = rotation * self.rotation;
+/// Name of a player that follows his sprite - it is not a child, so it does not rotate with him
+#[derive(Component)]
+pub struct PlayerLabel {
+    pub uuid: String,
+}
+
+/// The simulation of the backend stepped ahead with our own inputs - with the same `simulate` the backend is running.
+/// Every game-state of the backend corrects the prediction and the inputs it did not apply yet are replayed.
+#[derive(Resource, Default)]
+pub struct OwnPlayerPrediction {
+    simulation: Option<SimulationState>,
+    own_player_id: String,
+    previous_position: Vec2, // position before the last tick - used for smoothing the movement between two ticks
+    pending_inputs: VecDeque<(u32, Direction)>, // inputs the backend did not apply yet
+    last_sequence: u32,
+}
+
+impl OwnPlayerPrediction {
+    /// Starts over for a new round - the sequence numbers keep on counting
+    pub fn reset(&mut self) {
+        self.simulation = None;
+        self.pending_inputs.clear();
+    }
+
+    /// Our own player inside of the predicted simulation
+    pub fn state(&self) -> Option<&PlayerStates> {
+        self.simulation
+            .as_ref()?
+            .players
+            .iter()
+            .find(|p| p.id == self.own_player_id)
+    }
 
-            if self.current_direction != Direction::Right {
-                self.current_direction = Direction::Right;
+    /// Takes over the authoritative state of the backend and replays all inputs it did not apply yet
+    pub fn reconcile(&mut self, backend_state: &BackendState, own_player_id: &str) {
+        let Some(backend_player) = backend_state.players.iter().find(|p| p.id == own_player_id)
+        else {
+            return;
+        };
+        self.pending_inputs
+            .retain(|(sequence, _)| *sequence > backend_player.last_processed_input);
 
-                return Some(PlayerUpdateMessage {
-                    current_direction: Direction::Right,
-                });
+        let mut simulation = SimulationState::from_sync(
+            backend_state.players.clone(),
+            backend_state.power_ups.clone(),
+            backend_state.simulation.clone(),
+            backend_state.settings,
+        );
+        for (sequence, direction) in self.pending_inputs.iter() {
+            simulation = predict_tick(simulation, own_player_id, *sequence, direction.clone());
+        }
+
+        let is_first_state = self.simulation.is_none();
+        self.own_player_id = own_player_id.to_string();
+        self.simulation = Some(simulation);
+        if is_first_state {
+            if let Some(state) = self.state() {
+                self.previous_position = Vec2::new(state.position_x, state.position_y);
             }
         }
+    }
+}
+
+// the same tick the backend is doing - we don't know the inputs of the others, so they keep their direction
+fn predict_tick(
+    simulation: SimulationState,
+    own_player_id: &str,
+    sequence: u32,
+    direction: Direction,
+) -> SimulationState {
+    let input = PlayerInput {
+        player_id: own_player_id.to_string(),
+        sequence,
+        direction,
+    };
+    simulate(simulation, &[input])
+}
+
+fn read_direction(keys: &ButtonInput<KeyCode>) -> Direction {
+    let left_is_clicked = keys.pressed(KeyCode::ArrowLeft);
+    let right_is_clicked = keys.pressed(KeyCode::ArrowRight);
+
+    match (left_is_clicked, right_is_clicked) {
+        (true, false) => Direction::Left,
+        (false, true) => Direction::Right,
+        _ => Direction::Straight,
+    }
+}
+
+/// Runs once per backend-tick: samples the input, sends it to the backend and predicts our own player with it
+pub fn predict_own_player(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut prediction: ResMut<OwnPlayerPrediction>,
+    network_client: Res<NetworkClient>,
+    backend_state: Res<BackendState>,
+) {
+    let settings = &backend_state.settings;
+    let prediction = &mut *prediction;
+    let Some(state) = prediction.state() else {
+        return;
+    };
+
+    if !state.is_alive {
+        return;
+    }
+    let previous_position = Vec2::new(state.position_x, state.position_y);
+
+    let direction = read_direction(&keys);
+    prediction.last_sequence = prediction.last_sequence.wrapping_add(1);
+
+    network_client.send_message(NetworkMessage::PlayerUpdate(PlayerUpdateMessage {
+        sequence: prediction.last_sequence,
+        current_direction: direction.clone(),
+    }));
 
-        None
+    // if the backend does not answer anymore, we don't want to pile up inputs forever
+    prediction
+        .pending_inputs
+        .push_back((prediction.last_sequence, direction.clone()));
+    if prediction.pending_inputs.len() > settings.ticks(PENDING_INPUT_SECONDS) as usize {
+        prediction.pending_inputs.pop_front();
+    }
+
+    prediction.previous_position = previous_position;
+    if let Some(simulation) = prediction.simulation.take() {
+        let sequence = prediction.last_sequence;
+        prediction.simulation = Some(predict_tick(
+            simulation,
+            &prediction.own_player_id,
+            sequence,
+            direction,
+        ));
     }
 }
 
+/// Corrects our prediction whenever a new game-state arrived from the backend
+pub fn reconcile_own_player(
+    backend_state: Res<BackendState>,
+    connection_info: Res<ConnectionInfo>,
+    mut prediction: ResMut<OwnPlayerPrediction>,
+) {
+    prediction.reconcile(&backend_state, &connection_info.uuid);
+}
+
 pub fn move_player(
     mut query: Query<(&mut Player, &mut Transform), With<Player>>,
-    keys: Res<ButtonInput<KeyCode>>,
+    prediction: Res<OwnPlayerPrediction>,
+    snapshot_buffer: Res<SnapshotBuffer>,
+    fixed_time: Res<Time<Fixed>>,
     time: Res<Time>,
-    network_client: ResMut<NetworkClient>,
 ) {
-    for (mut player, mut transform) in query.iter_mut() {
-        if player.is_alive {
-            let mut direction = Vec3::ZERO;
+    let render_time = snapshot_buffer.render_time(time.elapsed_secs_f64());
 
+    for (player, mut transform) in query.iter_mut() {
+        if player.is_alive {
             if player.is_own_player {
-                let player_update = player.steer_player(&keys, time.delta_secs());
-                if let Some(player_update) = player_update {
-                    network_client.send_message(NetworkMessage::PlayerUpdate(player_update));
+                // our player is predicted once per tick - smooth the movement between two ticks
+                if let Some(state) = prediction.state() {
+                    let position = prediction.previous_position.lerp(
+                        Vec2::new(state.position_x, state.position_y),
+                        fixed_time.overstep_fraction(),
+                    );
+
+                    transform.translation.x = position.x;
+                    transform.translation.y = position.y;
+                    transform.rotation = Quat::from_rotation_z(state.direction);
                 }
+                continue;
+            }
+
+            // other players are rendered a bit in the past - between the game-states we received
+            if let Some((position, direction)) = snapshot_buffer.sample(&player.uuid, render_time) {
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+                transform.rotation = Quat::from_rotation_z(direction);
             }
+        }
+    }
+}
+
+/// Color of the player as it is rendered
+pub fn player_color(color: PlayerColor) -> Color {
+    let (red, green, blue) = color.rgb();
+    Color::srgb(red, green, blue)
+}
 
-            // move our player according to local input
-            direction.x += MOVE_SPEED * time.delta_secs();
-            direction = player.rotation.mul_vec3(direction);
+/// Moves the names along with the players and takes over names that changed
+pub fn move_player_labels(
+    players: Query<(&Player, &Transform), Without<PlayerLabel>>,
+    mut labels: Query<(&PlayerLabel, &mut Transform, &mut Text2d), Without<Player>>,
+    backend_state: Res<BackendState>,
+) {
+    for (label, mut label_transform, mut text) in labels.iter_mut() {
+        if let Some((_, player_transform)) = players.iter().find(|(p, _)| p.uuid == label.uuid) {
+            label_transform.translation.x = player_transform.translation.x;
+            label_transform.translation.y = player_transform.translation.y + LABEL_OFFSET;
+        }
 
-            if 0.0 < direction.length() {
-                transform.translation += direction;
-                transform.rotation = player.rotation;
+        if let Some(backend_player) = backend_state.players.iter().find(|p| p.id == label.uuid) {
+            if text.0 != backend_player.display_name {
+                text.0 = backend_player.display_name.clone();
             }
         }
     }
 }
 
+/// Spawns all players of the backend - without an own player id, every player is rendered as an enemy
 pub fn spawn_players_according_to_backend(
     mut commands: Commands,
-    backend_state: Res<BackendState>,
-    connection_info: Res<ConnectionInfo>,
+    backend_state: &BackendState,
+    own_player_id: Option<&str>,
     asset_server: Res<AssetServer>,
 ) {
-    let player_id = &connection_info.uuid;
-    for player in backend_state.players.iter().filter(|p| &p.id != player_id) {
+    // every player gets his name shown above him
+    for player in backend_state.players.iter() {
+        commands.spawn((
+            Text2d::new(player.display_name.clone()),
+            TextFont {
+                font_size: 16.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Transform::from_xyz(player.position_x, player.position_y + LABEL_OFFSET, 2.0),
+            PlayerLabel {
+                uuid: player.id.clone(),
+            },
+            OnRound,
+            OnGameScreen,
+        ));
+    }
+
+    for player in backend_state
+        .players
+        .iter()
+        .filter(|p| Some(p.id.as_str()) != own_player_id)
+    {
         let quat = Quat::from_rotation_z(player.direction);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("enemy.png")),
+            Sprite {
+                color: player_color(player.color),
+                ..Sprite::from_image(asset_server.load("enemy.png"))
+            },
             Transform::from_xyz(player.position_x, player.position_y, 1.0).with_rotation(quat),
             Player {
                 uuid: player.id.clone(),
-                current_direction: player.current_direction.clone(),
                 is_alive: player.is_alive,
                 is_own_player: false,
-                rotation: Quat::from_rotation_z(player.direction),
             },
+            OnRound,
             OnGameScreen,
         ));
     }
@@ -127,7 +310,7 @@ pub fn spawn_players_according_to_backend(
     let own_player = backend_state
         .players
         .iter()
-        .position(|p| &p.id == player_id)
+        .position(|p| Some(p.id.as_str()) == own_player_id)
         .map(|index| backend_state.players.get(index))
         .flatten();
 
@@ -135,16 +318,18 @@ pub fn spawn_players_according_to_backend(
         let quat = Quat::from_rotation_z(own_player.direction);
 
         commands.spawn((
-            Sprite::from_image(asset_server.load("player.png")),
+            Sprite {
+                color: player_color(own_player.color),
+                ..Sprite::from_image(asset_server.load("player.png"))
+            },
             Transform::from_xyz(own_player.position_x, own_player.position_y, 1.0)
                 .with_rotation(quat),
             Player {
                 uuid: own_player.id.clone(),
-                current_direction: Direction::Straight,
                 is_alive: own_player.is_alive,
                 is_own_player: true,
-                rotation: Quat::from_rotation_z(own_player.direction),
             },
+            OnRound,
             OnGameScreen,
         ));
     }
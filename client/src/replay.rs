@@ -0,0 +1,272 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use shared::models::replay::{Replay, ReplayChunkMessage};
+
+use crate::{
+    apply_game_state,
+    game::{FrontendLobbyState, OnGameScreen, OnRound},
+    show_lobby_state, BackendState, GameState,
+};
+
+// replays are kept relative to the working directory of the client
+const REPLAY_DIRECTORY: &str = "replays";
+
+// the arrow keys jump this far back or ahead
+const SEEK_SECONDS: f64 = 5.0;
+
+const PLAYBACK_SPEEDS: [f64; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const NORMAL_SPEED_INDEX: usize = 2;
+
+/// A replay that is played back with the same screens as a live match
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    next_frame: usize, // all frames before it are applied already
+    tick: f64,         // position of the playback - frames up to this tick are shown
+    speed_index: usize,
+    is_paused: bool,
+    is_reloading: bool, // after seeking, the screens are set up again before the frames are applied
+}
+
+#[derive(Component)]
+struct ReplayInfoText;
+
+pub fn replay_plugin(app: &mut App) {
+    app.init_resource::<IncomingReplay>()
+        .add_systems(
+            OnEnter(GameState::Game),
+            setup_replay_info.run_if(resource_exists::<ReplayPlayback>),
+        )
+        .add_systems(
+            Update,
+            (control_replay, advance_replay, update_replay_info)
+                .chain()
+                .run_if(in_state(GameState::Game).and(resource_exists::<ReplayPlayback>)),
+        );
+}
+
+/// Replay of the last match that the backend is still sending us in chunks
+#[derive(Resource, Default)]
+pub struct IncomingReplay {
+    replay: Option<Replay>,
+    next_chunk: u32,
+}
+
+impl IncomingReplay {
+    /// Puts the chunks back together and saves the replay as soon as the last one arrived
+    pub fn receive(&mut self, chunk: ReplayChunkMessage) {
+        // a new replay starts - a half received one is of no use anymore
+        if chunk.chunk_index == 0 {
+            self.replay = None;
+            self.next_chunk = 0;
+        }
+
+        let result = match self.replay.as_mut() {
+            Some(replay) => replay.append_chunk(self.next_chunk, chunk),
+            None if chunk.chunk_index == 0 => {
+                let is_complete = chunk.chunk_count <= 1;
+                self.replay = Some(chunk.replay);
+                Ok(is_complete)
+            }
+            None => Err("Missed the start of the replay".to_string()),
+        };
+
+        match result {
+            Ok(true) => {
+                if let Some(replay) = self.replay.take() {
+                    save_replay(&replay);
+                }
+            }
+            Ok(false) => self.next_chunk += 1,
+            Err(err) => {
+                // without the missing frames the replay can not be played back
+                println!("Dropping replay: {}", err);
+                self.replay = None;
+            }
+        }
+    }
+}
+
+/// Keeps the replay of a match we played, so it can be watched from the menu
+fn save_replay(replay: &Replay) {
+    match replay.save(Path::new(REPLAY_DIRECTORY)) {
+        Ok(path) => println!("Saved replay to {}", path.display()),
+        Err(err) => println!("Could not save replay: {}", err),
+    }
+}
+
+/// Plays back the replay that was saved last
+pub fn start_newest_replay(
+    commands: &mut Commands,
+    backend_state: &mut BackendState,
+    game_state: &mut NextState<GameState>,
+) -> Result<(), String> {
+    let path = Replay::newest_in(Path::new(REPLAY_DIRECTORY))
+        .ok_or_else(|| "No replay was saved yet".to_string())?;
+    let replay = Replay::load(&path)?;
+    if replay.frames.is_empty() {
+        return Err(format!("Replay {} is empty", path.display()));
+    }
+
+    reset_backend_state(backend_state);
+    // the replay is shown with the rules it was played with
+    backend_state.settings = replay.settings;
+    commands.insert_resource(ReplayPlayback {
+        tick: replay.first_tick() as f64,
+        replay,
+        next_frame: 0,
+        speed_index: NORMAL_SPEED_INDEX,
+        is_paused: false,
+        is_reloading: false,
+    });
+    game_state.set(GameState::Game);
+    Ok(())
+}
+
+// the replay is put together from scratch - nothing of the last match or replay may be left
+fn reset_backend_state(backend_state: &mut BackendState) {
+    backend_state.countdown = 0;
+    backend_state.players = Vec::new();
+    backend_state.awaiting_keyframe = false;
+    backend_state.tick = 0;
+    backend_state.power_ups = Vec::new();
+    backend_state.scores = Vec::new();
+    backend_state.round_results = None;
+}
+
+fn setup_replay_info(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 22.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        ReplayInfoText,
+        OnGameScreen,
+    ));
+}
+
+/// Space pauses, left and right seek, up and down change the speed and backspace leaves the replay
+fn control_replay(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut backend_state: ResMut<BackendState>,
+    mut lobby_state: ResMut<NextState<FrontendLobbyState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    round_entities: Query<Entity, With<OnRound>>,
+) {
+    if keys.just_pressed(KeyCode::Backspace) {
+        commands.remove_resource::<ReplayPlayback>();
+        reset_backend_state(&mut backend_state);
+        lobby_state.set(FrontendLobbyState::Loading);
+        game_state.set(GameState::Menu);
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Space) {
+        playback.is_paused = !playback.is_paused;
+    }
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        playback.speed_index = (playback.speed_index + 1).min(PLAYBACK_SPEEDS.len() - 1);
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        playback.speed_index = playback.speed_index.saturating_sub(1);
+    }
+
+    let seek_ticks = SEEK_SECONDS * playback.replay.settings.ticks_per_second as f64;
+    let target_tick = if keys.just_pressed(KeyCode::ArrowLeft) {
+        playback.tick - seek_ticks
+    } else if keys.just_pressed(KeyCode::ArrowRight) {
+        playback.tick + seek_ticks
+    } else {
+        return;
+    };
+
+    // the round is built up again from its keyframe - everything that is shown right now is removed
+    let target_tick = target_tick.clamp(
+        playback.replay.first_tick() as f64,
+        playback.replay.last_tick() as f64,
+    );
+    playback.tick = target_tick;
+    playback.next_frame = playback.replay.keyframe_index_before(target_tick as u64);
+    playback.is_reloading = true;
+
+    for entity in &round_entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    reset_backend_state(&mut backend_state);
+    lobby_state.set(FrontendLobbyState::Loading);
+}
+
+/// Applies all frames up to the position of the playback, like the game-states of the backend are applied
+fn advance_replay(
+    mut playback: ResMut<ReplayPlayback>,
+    mut backend_state: ResMut<BackendState>,
+    mut lobby_state: ResMut<NextState<FrontendLobbyState>>,
+    current_lobby_state: Res<State<FrontendLobbyState>>,
+    time: Res<Time>,
+) {
+    let playback = &mut *playback;
+
+    // the screens of the lobby-state we left have to be gone first
+    if playback.is_reloading {
+        if *current_lobby_state.get() != FrontendLobbyState::Loading {
+            return;
+        }
+        playback.is_reloading = false;
+    }
+
+    if !playback.is_paused {
+        let elapsed_ticks = time.delta_secs_f64()
+            * playback.replay.settings.ticks_per_second as f64
+            * PLAYBACK_SPEEDS[playback.speed_index];
+        playback.tick = (playback.tick + elapsed_ticks).min(playback.replay.last_tick() as f64);
+    }
+
+    let now_millis = (time.elapsed_secs_f64() * 1000.0) as u64;
+    while let Some(frame) = playback.replay.frames.get(playback.next_frame) {
+        if frame.tick as f64 > playback.tick {
+            break;
+        }
+
+        // other players are interpolated by the time a frame is shown at - not the time it was recorded at
+        let mut frame = frame.clone();
+        frame.server_time_millis = now_millis;
+
+        let frame_lobby_state = frame.lobby_state.clone();
+        apply_game_state(&mut backend_state, frame);
+        show_lobby_state(&mut backend_state, &mut lobby_state, frame_lobby_state);
+        playback.next_frame += 1;
+    }
+}
+
+fn update_replay_info(
+    mut query: Query<&mut Text, With<ReplayInfoText>>,
+    playback: Res<ReplayPlayback>,
+) {
+    let first_tick = playback.replay.first_tick() as f64;
+    let ticks_per_second = playback.replay.settings.ticks_per_second as f64;
+    let position_seconds = (playback.tick - first_tick) / ticks_per_second;
+    let total_seconds = (playback.replay.last_tick() as f64 - first_tick) / ticks_per_second;
+    let paused = if playback.is_paused { " (paused)" } else { "" };
+
+    let info = format!(
+        "Replay {:.0}s / {:.0}s - {}x{}\nSpace: pause, Left/Right: seek, Up/Down: speed, Backspace: back to the menu",
+        position_seconds, total_seconds, PLAYBACK_SPEEDS[playback.speed_index], paused
+    );
+
+    for mut text in &mut query {
+        if text.0 != info {
+            text.0 = info.clone();
+        }
+    }
+}
@@ -1,22 +1,30 @@
 use bevy::prelude::*;
 
-use crate::{despawn_screen, player::ConnectionInfo};
+use crate::despawn_screen;
 
 use super::GameState;
 
+// the logo is shown this long before the player picks the server
+const SPLASH_SECONDS: f32 = 1.5;
+
 pub fn splash_plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::Splash), splash_setup)
-        .add_systems(
-            Update,
-            check_for_connection_info.run_if(in_state(GameState::Splash)),
-        )
+        .add_systems(Update, countdown.run_if(in_state(GameState::Splash)))
         .add_systems(OnExit(GameState::Splash), despawn_screen::<OnSplashScreen>);
 }
 
 #[derive(Component)]
 struct OnSplashScreen;
 
+#[derive(Resource, Deref, DerefMut)]
+struct SplashTimer(Timer);
+
 fn splash_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SplashTimer(Timer::from_seconds(
+        SPLASH_SECONDS,
+        TimerMode::Once,
+    )));
+
     let icon = asset_server.load("logo.png");
     // Display the logo
     commands
@@ -41,13 +49,14 @@ fn splash_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         });
 }
 
-// turn to menu once we are connected
-fn check_for_connection_info(
-    connection_info: Option<Res<ConnectionInfo>>,
+// turn to the connect screen once the logo was shown long enough
+fn countdown(
     mut game_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+    mut timer: ResMut<SplashTimer>,
 ) {
-    if connection_info.is_some() {
+    if timer.tick(time.delta()).finished() {
         // end splash screen
-        game_state.set(GameState::Menu);
+        game_state.set(GameState::Connect);
     }
 }
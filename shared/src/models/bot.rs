@@ -0,0 +1,133 @@
+use super::{
+    direction::Direction, game_settings::GameSettings, network_message::BotDifficulty,
+    player_states::PlayerStates, simulation_rng::SimulationRng,
+};
+
+// directions a bot can choose from - the first one wins if they are equally good
+const DIRECTIONS: [Direction; 3] = [Direction::Straight, Direction::Left, Direction::Right];
+
+/// Player that is steered by the computer - it sends its inputs the same way a player does
+pub struct Bot {
+    difficulty: BotDifficulty,
+    sequence: u32,
+    direction: Direction,
+    ticks_until_decision: u32,
+}
+
+impl Bot {
+    pub fn new(difficulty: BotDifficulty) -> Self {
+        Self {
+            difficulty,
+            sequence: 0,
+            direction: Direction::Straight,
+            ticks_until_decision: 0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.difficulty {
+            BotDifficulty::Easy => "Easy Bot",
+            BotDifficulty::Hard => "Hard Bot",
+        }
+    }
+
+    // how far the bot looks ahead along its heading
+    fn lookahead_seconds(&self) -> f32 {
+        match self.difficulty {
+            BotDifficulty::Easy => 0.24,
+            BotDifficulty::Hard => 0.8,
+        }
+    }
+
+    // easy bots only think about their direction from time to time
+    fn seconds_between_decisions(&self) -> f32 {
+        match self.difficulty {
+            BotDifficulty::Easy => 0.1,
+            BotDifficulty::Hard => 0.0,
+        }
+    }
+
+    // chance that the bot takes another safe direction, so it does not only drive straight ahead
+    fn wander_chance(&self) -> f64 {
+        match self.difficulty {
+            BotDifficulty::Easy => 0.08,
+            BotDifficulty::Hard => 0.03,
+        }
+    }
+
+    /// Decides where the bot steers next - returns the input it sends together with its sequence number
+    pub fn next_input(
+        &mut self,
+        player: &PlayerStates,
+        players: &[PlayerStates],
+        settings: &GameSettings,
+        rng: &mut SimulationRng,
+    ) -> (u32, Direction) {
+        if self.ticks_until_decision > 0 {
+            self.ticks_until_decision -= 1;
+        } else {
+            self.ticks_until_decision =
+                (self.seconds_between_decisions() * settings.ticks_per_second).round() as u32;
+            self.direction = self.choose_direction(player, players, settings, rng);
+        }
+
+        self.sequence = self.sequence.wrapping_add(1);
+        (self.sequence, self.direction.clone())
+    }
+
+    // the direction the bot survives the longest with - it keeps its current one if others are not better
+    fn choose_direction(
+        &self,
+        player: &PlayerStates,
+        players: &[PlayerStates],
+        settings: &GameSettings,
+        rng: &mut SimulationRng,
+    ) -> Direction {
+        let survived_ticks: Vec<u32> = DIRECTIONS
+            .iter()
+            .map(|direction| self.survived_ticks(player, players, settings, direction.clone()))
+            .collect();
+        let best_ticks = survived_ticks.iter().copied().max().unwrap_or(0);
+
+        let best_directions: Vec<&Direction> = DIRECTIONS
+            .iter()
+            .zip(survived_ticks)
+            .filter(|(_, ticks)| *ticks == best_ticks)
+            .map(|(direction, _)| direction)
+            .collect();
+
+        if best_directions.len() > 1 && rng.chance(self.wander_chance()) {
+            let index = rng.index(best_directions.len());
+            return best_directions[index].clone();
+        }
+
+        if best_directions.contains(&&self.direction) {
+            return self.direction.clone();
+        }
+        best_directions[0].clone()
+    }
+
+    // steers a copy of the player into the direction and counts the ticks until he would crash
+    fn survived_ticks(
+        &self,
+        player: &PlayerStates,
+        players: &[PlayerStates],
+        settings: &GameSettings,
+        direction: Direction,
+    ) -> u32 {
+        let mut probe = player.without_trail();
+        probe.set_direction(direction);
+
+        let lookahead_ticks = settings.ticks(self.lookahead_seconds());
+        for tick in 0..lookahead_ticks {
+            probe.steer_player(settings);
+            probe.advance_position(settings);
+
+            if probe.would_crash(players, settings) {
+                return tick;
+            }
+        }
+
+        lookahead_ticks
+    }
+}
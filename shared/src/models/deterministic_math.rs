@@ -0,0 +1,41 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+/// Sine and cosine of the angle that only use basic arithmetic.
+/// The trigonometric functions of the platform may round differently, these give the same result everywhere.
+pub fn sin_cos(angle: f32) -> (f32, f32) {
+    // bring the angle into [-PI, PI]
+    let mut x = angle % TAU;
+    if x > PI {
+        x -= TAU;
+    } else if x < -PI {
+        x += TAU;
+    }
+
+    // and further into [-PI/2, PI/2] - mirroring the angle keeps the sine, but flips the cosine
+    let (x, cos_sign) = if x > FRAC_PI_2 {
+        (PI - x, -1.0)
+    } else if x < -FRAC_PI_2 {
+        (-PI - x, -1.0)
+    } else {
+        (x, 1.0)
+    };
+
+    // taylor series - inside of [-PI/2, PI/2] the left out terms are below the precision of a f32
+    let x2 = x * x;
+    let sin = x
+        * (1.0
+            - x2 / 6.0
+                * (1.0
+                    - x2 / 20.0
+                        * (1.0
+                            - x2 / 42.0
+                                * (1.0 - x2 / 72.0 * (1.0 - x2 / 110.0 * (1.0 - x2 / 156.0))))));
+    let cos = 1.0
+        - x2 / 2.0
+            * (1.0
+                - x2 / 12.0
+                    * (1.0
+                        - x2 / 30.0 * (1.0 - x2 / 56.0 * (1.0 - x2 / 90.0 * (1.0 - x2 / 132.0)))));
+
+    (sin, cos_sign * cos)
+}
@@ -0,0 +1,48 @@
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 16;
+
+/// Trims the chosen name and checks that it can be shown to the other players
+pub fn validate_display_name(display_name: &str) -> Result<String, String> {
+    let display_name = display_name.trim();
+
+    if display_name.is_empty() {
+        return Err("Display name must not be empty".to_string());
+    }
+    if display_name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+        return Err(format!(
+            "Display name must not be longer than {} characters",
+            MAX_DISPLAY_NAME_LENGTH
+        ));
+    }
+    if !display_name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_')
+    {
+        return Err("Display name contains invalid characters".to_string());
+    }
+
+    Ok(display_name.to_string())
+}
+
+/// Appends a number to the name until none of the given names is the same anymore.
+/// The name is shortened to make room for the number, so it still passes `validate_display_name`.
+pub fn unique_display_name(display_name: &str, taken_names: &[&str]) -> String {
+    let mut unique_name = display_name.to_string();
+    let mut number = 1;
+
+    while taken_names.contains(&unique_name.as_str()) {
+        number += 1;
+        let suffix = format!(" {}", number);
+        let base: String = display_name
+            .chars()
+            .take(MAX_DISPLAY_NAME_LENGTH.saturating_sub(suffix.len()))
+            .collect();
+        unique_name = format!("{}{}", base.trim_end(), suffix);
+    }
+
+    unique_name
+}
+
+/// Name a player gets as long as he did not choose one
+pub fn default_display_name(uuid: &str) -> String {
+    format!("Player {}", uuid.chars().take(4).collect::<String>())
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use super::{
+    network_message::{DeathRecord, Placement, PlayerScore, RoundResults},
+    player_states::{DeathCause, LobbyState},
+    simulation::{simulate, PlayerInput, SimulationState},
+};
+
+pub const ROUNDS_PER_MATCH: u32 = 5;
+const COUNTDOWN_SECONDS: u32 = 5;
+const SCOREBOARD_SECONDS: u32 = 5;
+
+/// The rules of a match - counting down, running the rounds, awarding points and finishing the match.
+/// It knows nothing about connections, so it runs the same inside of a lobby and without any network.
+#[derive(Clone)]
+pub struct GameMatch {
+    pub simulation: SimulationState, // players and power-ups - everything the rules of a round depend on
+    pub lobby_state: LobbyState,
+    pub tick: u64,  // only increases while a match is played - it is never reset
+    pub round: u32, // round of the current match - starting with 1
+    pub scores: HashMap<String, u32>, // points of every player in the current match
+    ticks_until_next_second: u32, // the countdown and the scoreboard are counted down in seconds
+    round_started_at_tick: u64,
+    deaths: Vec<(String, DeathRecord)>, // players that died in the current round - in the order they died
+}
+
+impl Default for GameMatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameMatch {
+    pub fn new() -> Self {
+        GameMatch {
+            simulation: SimulationState::default(),
+            lobby_state: LobbyState::Waiting,
+            tick: 0,
+            round: 0,
+            scores: HashMap::new(),
+            ticks_until_next_second: 0,
+            round_started_at_tick: 0,
+            deaths: Vec::new(),
+        }
+    }
+
+    /// A match is running from its first countdown until its last round is over
+    pub fn is_running(&self) -> bool {
+        !matches!(self.lobby_state, LobbyState::Waiting | LobbyState::Finished)
+    }
+
+    /// Starts a new match - all scores are reset and the countdown of the first round begins.
+    /// With the seed and the inputs of every tick the match can be played again.
+    pub fn start(&mut self, seed: u64) {
+        self.scores.clear();
+        self.round = 1;
+        self.simulation.reseed(seed);
+        self.start_round();
+    }
+
+    fn start_round(&mut self) {
+        self.simulation.start_round();
+        self.deaths.clear();
+        self.lobby_state = LobbyState::Countdown(COUNTDOWN_SECONDS);
+        self.ticks_until_next_second = self.ticks_per_second_counted();
+    }
+
+    /// Moves the match one tick further - counts down, runs the game or waits for the next round.
+    /// The inputs are only applied while the game is running.
+    pub fn advance(&mut self, inputs: &[PlayerInput]) {
+        if !self.is_running() {
+            return;
+        }
+        self.tick += 1;
+
+        match self.lobby_state {
+            LobbyState::Countdown(seconds) => {
+                if self.count_down_second() {
+                    if seconds > 1 {
+                        self.lobby_state = LobbyState::Countdown(seconds - 1);
+                    } else {
+                        self.lobby_state = LobbyState::Running;
+                        self.round_started_at_tick = self.tick;
+                    }
+                }
+            }
+            LobbyState::Running => self.next_step(inputs),
+            LobbyState::RoundOver(seconds) => {
+                if self.count_down_second() {
+                    if seconds > 1 {
+                        self.lobby_state = LobbyState::RoundOver(seconds - 1);
+                    } else {
+                        self.round += 1;
+                        self.start_round();
+                    }
+                }
+            }
+            LobbyState::Waiting | LobbyState::Finished => {}
+        }
+    }
+
+    fn ticks_per_second_counted(&self) -> u32 {
+        self.simulation.settings.ticks(1.0)
+    }
+
+    // returns true whenever a whole second passed
+    fn count_down_second(&mut self) -> bool {
+        self.ticks_until_next_second = self.ticks_until_next_second.saturating_sub(1);
+        if self.ticks_until_next_second == 0 {
+            self.ticks_until_next_second = self.ticks_per_second_counted();
+            return true;
+        }
+        false
+    }
+
+    fn next_step(&mut self, inputs: &[PlayerInput]) {
+        let alive_before: Vec<bool> = self.simulation.players.iter().map(|p| p.is_alive).collect();
+
+        // the rules of the game are the same on the server, in the simulator and on the clients
+        self.simulation = simulate(std::mem::take(&mut self.simulation), inputs);
+
+        // everyone that survives the collisions gets points
+        self.record_deaths(&alive_before);
+        self.award_points(&alive_before);
+
+        // check if the round is over (is over when only 1 or none player are alive)
+        let alive_players_count = self
+            .simulation
+            .players
+            .iter()
+            .filter(|p| p.is_alive)
+            .count();
+        if alive_players_count <= 1 {
+            self.finish_round();
+        }
+    }
+
+    /// Every player that survives the death of another one gets a point for it
+    fn award_points(&mut self, alive_before: &[bool]) {
+        let deaths = self
+            .simulation
+            .players
+            .iter()
+            .zip(alive_before)
+            .filter(|(p, &was_alive)| was_alive && !p.is_alive)
+            .count() as u32;
+
+        if deaths == 0 {
+            return;
+        }
+
+        for player in self.simulation.players.iter().filter(|p| p.is_alive) {
+            *self.scores.entry(player.id.clone()).or_insert(0) += deaths;
+        }
+    }
+
+    /// Remembers when and why the players died that were alive before the last collision-check
+    fn record_deaths(&mut self, alive_before: &[bool]) {
+        for (player, &was_alive) in self.simulation.players.iter().zip(alive_before) {
+            if was_alive && !player.is_alive {
+                let cause = player.death_cause.clone().unwrap_or(DeathCause::Wall);
+                self.deaths.push((
+                    player.id.clone(),
+                    DeathRecord {
+                        tick: self.tick,
+                        cause,
+                    },
+                ));
+            }
+        }
+    }
+
+    /// Shows the scoreboard before the next round - after the last round the match is finished
+    fn finish_round(&mut self) {
+        if self.round < ROUNDS_PER_MATCH {
+            self.lobby_state = LobbyState::RoundOver(SCOREBOARD_SECONDS);
+            self.ticks_until_next_second = self.ticks_per_second_counted();
+        } else {
+            self.lobby_state = LobbyState::Finished;
+        }
+    }
+
+    /// Survivors come first, then the players in the reverse order they died
+    pub fn round_results(&self) -> RoundResults {
+        let mut placements: Vec<Placement> = self
+            .simulation
+            .players
+            .iter()
+            .filter(|p| p.is_alive)
+            .map(|p| Placement {
+                player_id: p.id.clone(),
+                place: 1,
+                death: None,
+            })
+            .collect();
+
+        let mut previous_tick = None;
+        let mut place = 1;
+        for (player_id, death) in self.deaths.iter().rev() {
+            // players that died in the same tick share their place
+            if previous_tick != Some(death.tick) {
+                place = placements.len() as u32 + 1;
+                previous_tick = Some(death.tick);
+            }
+
+            placements.push(Placement {
+                player_id: player_id.clone(),
+                place,
+                death: Some(death.clone()),
+            });
+        }
+
+        RoundResults {
+            started_at_tick: self.round_started_at_tick,
+            placements,
+        }
+    }
+
+    /// Scores of all players in the match - the best player comes first
+    pub fn scores(&self) -> Vec<PlayerScore> {
+        let mut scores: Vec<PlayerScore> = self
+            .simulation
+            .players
+            .iter()
+            .map(|p| PlayerScore {
+                player_id: p.id.clone(),
+                points: self.scores.get(&p.id).copied().unwrap_or_default(),
+            })
+            .collect();
+        scores.sort_by_key(|score| std::cmp::Reverse(score.points));
+        scores
+    }
+}
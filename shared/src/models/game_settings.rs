@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+// thin trails are easier to slip past - their collision-radius is this much of the normal one
+const THIN_TRAIL_RADIUS_FACTOR: f32 = 0.6;
+
+/// Rules of the game a lobby is played with - the host can change them before the match starts.
+/// The server simulates with them and the clients render and predict with them.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields)] // settings that are left out keep their default value - misspelled ones are rejected
+pub struct GameSettings {
+    pub board_width: f32,
+    pub board_height: f32,
+    pub move_speed: f32,       // distance a player moves per second
+    pub rotation_speed: f32,   // radians a player turns per second
+    pub ticks_per_second: f32, // how often the server computes a new game-state
+    pub collision_radius: f32, // players crash if they come closer to a trail point than this
+    pub unspawnable_edge: f32, // nobody is spawned closer to the walls than this
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            board_width: 1000.0,
+            board_height: 1000.0,
+            move_speed: 200.0,
+            rotation_speed: 2.5,
+            ticks_per_second: 50.0,
+            collision_radius: 20.0,
+            unspawnable_edge: 150.0,
+        }
+    }
+}
+
+impl GameSettings {
+    /// Checks that a match can be played with the settings - the error tells which value is out of range
+    pub fn validate(&self) -> Result<(), String> {
+        check_range("Board width", self.board_width, 400.0, 4000.0)?;
+        check_range("Board height", self.board_height, 400.0, 4000.0)?;
+        check_range("Move speed", self.move_speed, 50.0, 800.0)?;
+        check_range("Rotation speed", self.rotation_speed, 0.5, 10.0)?;
+        check_range("Tick rate", self.ticks_per_second, 10.0, 120.0)?;
+        check_range("Collision radius", self.collision_radius, 5.0, 60.0)?;
+
+        // there has to be some room left in the middle of the board to spawn the players
+        let max_edge = self.board_width.min(self.board_height) / 2.0 - self.collision_radius;
+        check_range("Unspawnable edge", self.unspawnable_edge, 0.0, max_edge)?;
+
+        Ok(())
+    }
+
+    pub fn move_speed_per_tick(&self) -> f32 {
+        self.move_speed / self.ticks_per_second
+    }
+
+    pub fn rotation_speed_per_tick(&self) -> f32 {
+        self.rotation_speed / self.ticks_per_second
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_secs_f32(1.0 / self.ticks_per_second)
+    }
+
+    pub fn thin_trail_collision_radius(&self) -> f32 {
+        self.collision_radius * THIN_TRAIL_RADIUS_FACTOR
+    }
+
+    /// How many ticks the given time takes - at least one
+    pub fn ticks(&self, seconds: f32) -> u32 {
+        ((seconds * self.ticks_per_second).round() as u32).max(1)
+    }
+}
+
+fn check_range(name: &str, value: f32, min: f32, max: f32) -> Result<(), String> {
+    if !(min..=max).contains(&value) {
+        return Err(format!(
+            "{} has to be between {} and {} (was {})",
+            name, min, max, value
+        ));
+    }
+    Ok(())
+}
@@ -1,18 +1,30 @@
+pub mod bot;
+pub mod deterministic_math;
 pub mod direction;
+pub mod display_name;
+pub mod game_match;
+pub mod game_settings;
 pub mod network_message;
+pub mod player_color;
 pub mod player_states;
+pub mod power_up;
+pub mod replay;
+pub mod simulation;
+pub mod simulation_rng;
+pub mod trail_grid;
+pub mod wire_format;
 
 pub const PORT: &str = "11255";
 
-const COLLISION_RADIUS: f32 = 20.0;
+// board size, speeds, tick rate and collision-radius are part of the game-settings of each lobby
 
-pub const MOVE_SPEED: f32 = 200.0;
-pub const ROTATION_SPEED: f32 = 2.5;
-pub const TICKS_PER_SECOND: f32 = 50.0; // Backend ticks per second
-pub const MILLIS_PER_TICK: f32 = 1000.0 / TICKS_PER_SECOND;
-pub const ROTATION_SPEED_PER_TICK: f32 = ROTATION_SPEED / TICKS_PER_SECOND;
-pub const MOVE_SPEED_PER_TICK: f32 = MOVE_SPEED / TICKS_PER_SECOND;
+// players leave gaps in their trail from time to time - the length of a gap must allow others to slip through
+pub const MIN_GAP_SECONDS: f32 = 0.24;
+pub const MAX_GAP_SECONDS: f32 = 0.36;
+pub const MIN_SECONDS_BETWEEN_GAPS: f32 = 2.0;
+pub const MAX_SECONDS_BETWEEN_GAPS: f32 = 5.0;
 
-pub const GAME_BOARD_WIDTH: f32 = 1000.0;
-pub const GAME_BOARD_HEIGHT: f32 = 1000.0;
-pub const UNSPAWNABLE_EDGE: f32 = 150.0;
+// power-ups are spawned from time to time until there are enough of them on the board
+pub const MAX_POWER_UPS_ON_BOARD: usize = 3;
+pub const MIN_SECONDS_BETWEEN_POWER_UPS: f32 = 3.0;
+pub const MAX_SECONDS_BETWEEN_POWER_UPS: f32 = 8.0;
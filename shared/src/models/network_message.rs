@@ -2,31 +2,179 @@ use serde::{Deserialize, Serialize};
 
 use super::{
     direction::Direction,
-    player_states::{LobbyState, PlayerStates},
+    game_settings::GameSettings,
+    player_color::PlayerColor,
+    player_states::{DeathCause, LobbyState, PlayerStates, TrailPoint},
+    power_up::PowerUp,
+    replay::ReplayChunkMessage,
+    simulation::SimulationSync,
 };
 
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum NetworkMessage {
     ConnectionInfo(ConnectionInfoMessage), // info that is send to the user when he is connecting
-    RequestStart(()),                      // player requests start of game
+    RequestStart(()),                      // host forces the start of the game
     PlayerUpdate(PlayerUpdateMessage),     // the update that the player sents to the server
     GameState(GameStateMessage),           // cyclic update of the game
+    ListLobbies(()),                       // player requests a list of all open lobbies
+    LobbyList(LobbyListMessage),           // list of all open lobbies that is send to the player
+    CreateLobby(CreateLobbyMessage),       // player opens up a new lobby and joins it
+    JoinLobby(JoinLobbyMessage),           // player joins an already existing lobby
+    LeaveLobby(()),                        // player leaves the lobby he is currently in
+    StateAck(StateAckMessage),             // player acknowledges that he applied a game-state
+    RequestResync(()), // player lost track of the trails and needs a full game-state
+    Hello(HelloMessage), // player tells us the name he wants to be shown with
+    ToggleReady(()),   // player is ready to play - the match starts when everyone is
+    LobbyStatus(LobbyStatusMessage), // who is inside the lobby and who is ready
+    RequestRejected(RequestRejectedMessage), // the request of the player could not be fulfilled
+    AddBot(AddBotMessage), // host adds a player to the lobby that is steered by the server
+    ReplayChunk(ReplayChunkMessage), // part of the recording of the match that just finished
+    ChangeSettings(GameSettings), // host changes the rules the next match is played with
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct ConnectionInfoMessage {
     pub player_id: String,
     pub players_connected: u32,
+    pub lobby_id: Option<String>, // none as long as the player did not join a lobby
+    pub display_name: String, // name the player is shown with - can differ from the one he asked for
+    pub color: PlayerColor, // color the player is shown with - can differ from the one he asked for
+    pub resume_token: String, // lets the player take his slot back after losing the connection
+    pub is_spectator: bool, // spectators only watch - they have no player inside the lobby
 }
 
+/// Sent to everyone inside the lobby whenever a player joins, leaves or toggles ready
 #[derive(Serialize, Deserialize)]
+pub struct LobbyStatusMessage {
+    pub host_id: Option<String>, // the host can start the match without everyone being ready
+    pub members: Vec<LobbyMember>,
+    pub spectators: Vec<String>, // names of everyone who is only watching
+    pub settings: GameSettings,  // rules the next match is played with
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LobbyMember {
+    pub player_id: String,
+    pub display_name: String,
+    pub color: PlayerColor,
+    pub is_ready: bool,
+    pub is_connected: bool, // false while the player lost his connection and may still come back
+    pub is_bot: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RequestRejectedMessage {
+    pub reason: String,
+}
+
+/// How far ahead a bot looks for trails and walls
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum BotDifficulty {
+    Easy,
+    Hard,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AddBotMessage {
+    pub difficulty: BotDifficulty,
+}
+
+/// Both fields are optional - the player keeps what he has for the ones that are left out
+#[derive(Serialize, Deserialize)]
+pub struct HelloMessage {
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub preferred_color: Option<PlayerColor>,
+}
+
+/// Player-states are sent without their trails.
+/// The trails are only sent as the points that were appended since the last game-state the player acknowledged.
+/// A keyframe contains the whole trails, it is sent on joining or when a player requested a resync.
+#[derive(Serialize, Deserialize, Clone)]
 pub struct GameStateMessage {
+    pub tick: u64,               // increases with every game-state the lobby sends
+    pub server_time_millis: u64, // time of the server when the tick was computed (millis since unix-epoch)
+    pub is_keyframe: bool,
     pub lobby_state: LobbyState,
     pub player_states: Vec<PlayerStates>,
+    pub trail_updates: Vec<TrailUpdate>,
+    pub power_ups: Vec<PowerUp>, // power-ups lying on the board
+    pub round: u32,              // round of the match that is played - starting with 1
+    pub rounds_per_match: u32,
+    pub scores: Vec<PlayerScore>, // points of the match so far - the best player comes first
+    pub round_results: Option<RoundResults>, // only sent when a round is over
+    pub simulation: SimulationSync, // lets the client step the simulation ahead on its own
+}
+
+/// How the last round went - the player that survived the longest comes first
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RoundResults {
+    pub started_at_tick: u64,
+    pub placements: Vec<Placement>,
+}
+
+/// Players that died in the same tick share their place
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Placement {
+    pub player_id: String,
+    pub place: u32,
+    pub death: Option<DeathRecord>, // none for the player that survived
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DeathRecord {
+    pub tick: u64,
+    pub cause: DeathCause,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlayerScore {
+    pub player_id: String,
+    pub points: u32,
+}
+
+/// Trail points of a player starting at the given index - everything from that index on is replaced
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrailUpdate {
+    pub player_id: String,
+    pub start_index: u32,
+    pub points: Vec<TrailPoint>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StateAckMessage {
+    pub tick: u64,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct PlayerUpdateMessage {
+    pub sequence: u32, // increases with every tick, so the player knows which inputs the server already applied
     pub current_direction: Direction,
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct LobbyListMessage {
+    pub lobbies: Vec<LobbyInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LobbyInfo {
+    pub lobby_id: String,
+    pub name: String,
+    pub players_connected: u32,
+    pub lobby_state: LobbyState,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateLobbyMessage {
+    pub name: String, // if left empty the server will choose a name
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct JoinLobbyMessage {
+    pub lobby_id: String,
+    #[serde(default)]
+    pub as_spectator: bool, // spectators get every game-state but are never spawned into a round
+}
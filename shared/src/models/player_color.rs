@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// Color of a player - his sprite and trail are painted with it
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum PlayerColor {
+    #[default]
+    Pink,
+    Cyan,
+    Yellow,
+    Green,
+    Orange,
+    Blue,
+    Purple,
+    Red,
+}
+
+impl PlayerColor {
+    pub const ALL: [PlayerColor; 8] = [
+        PlayerColor::Pink,
+        PlayerColor::Cyan,
+        PlayerColor::Yellow,
+        PlayerColor::Green,
+        PlayerColor::Orange,
+        PlayerColor::Blue,
+        PlayerColor::Purple,
+        PlayerColor::Red,
+    ];
+
+    /// Red, green and blue of the color (0.0 to 1.0)
+    pub fn rgb(&self) -> (f32, f32, f32) {
+        match self {
+            PlayerColor::Pink => (0.9, 0.2, 0.5),
+            PlayerColor::Cyan => (0.1, 0.85, 0.9),
+            PlayerColor::Yellow => (0.95, 0.85, 0.1),
+            PlayerColor::Green => (0.2, 0.85, 0.3),
+            PlayerColor::Orange => (0.95, 0.5, 0.1),
+            PlayerColor::Blue => (0.25, 0.4, 0.95),
+            PlayerColor::Purple => (0.6, 0.3, 0.9),
+            PlayerColor::Red => (0.9, 0.15, 0.15),
+        }
+    }
+
+    /// The color that comes after this one - used for cycling through all colors
+    pub fn next(&self) -> Self {
+        let index = PlayerColor::ALL
+            .iter()
+            .position(|color| color == self)
+            .unwrap_or_default();
+        PlayerColor::ALL[(index + 1) % PlayerColor::ALL.len()]
+    }
+
+    /// The preferred color if nobody else has it, otherwise the next color after it that is still free.
+    /// Without a preferred color the first free one is picked. If all colors are taken, they are used a second time.
+    pub fn pick_free(preferred: Option<PlayerColor>, taken_colors: &[PlayerColor]) -> Self {
+        let start = preferred
+            .and_then(|preferred| {
+                PlayerColor::ALL
+                    .iter()
+                    .position(|color| *color == preferred)
+            })
+            .unwrap_or_default();
+
+        (0..PlayerColor::ALL.len())
+            .map(|offset| PlayerColor::ALL[(start + offset) % PlayerColor::ALL.len()])
+            .find(|color| !taken_colors.contains(color))
+            .unwrap_or(PlayerColor::ALL[taken_colors.len() % PlayerColor::ALL.len()])
+    }
+}
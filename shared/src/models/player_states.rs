@@ -1,67 +1,219 @@
-use std::f32::consts::PI;
+use std::{collections::VecDeque, f32::consts::PI};
 
 use serde::{Deserialize, Serialize};
 
-use super::{direction::Direction, COLLISION_RADIUS, MOVE_SPEED_PER_TICK, ROTATION_SPEED_PER_TICK};
+use super::{
+    deterministic_math::sin_cos,
+    direction::Direction,
+    game_settings::GameSettings,
+    player_color::PlayerColor,
+    power_up::{ActiveEffect, EffectKind},
+    trail_grid::TrailGrid,
+};
 
-#[derive(Serialize, Deserialize, PartialEq)]
+// inputs that are buffered longer than this are dropped, otherwise the player would react more and more delayed
+const MAX_BUFFERED_INPUTS: usize = 10;
+
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub enum LobbyState {
     Waiting,
     Countdown(u32),
     Running,
-    Finished,
+    RoundOver(u32), // the scoreboard is shown - the next round starts after the given seconds
+    Finished,       // all rounds of the match were played
+}
+
+/// What a player crashed into
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum DeathCause {
+    OwnTrail,
+    PlayerTrail(String), // id of the player the trail belongs to
+    Wall,
+}
+
+/// Point of a trail - thin points are left while the player has the thin-trail effect
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct TrailPoint {
+    pub x: f32,
+    pub y: f32,
+    pub is_thin: bool,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct PlayerStates {
     pub id: String,
+    pub display_name: String,
+    pub color: PlayerColor,
     pub position_x: f32,
     pub position_y: f32,
     pub direction: f32,
     pub is_alive: bool,
-    pub trail: Vec<(f32, f32)>,
+    #[serde(skip)]
+    pub trail: Vec<TrailPoint>, // trails are sent separately as trail updates
     pub current_direction: Direction,
+    #[serde(skip)]
+    pub trail_grid: TrailGrid, // only needed for collision-checks - clients fill it with the trail updates
+    pub last_processed_input: u32, // sequence number of the newest input that was applied
+    pub is_drawing_trail: bool,    // false while the player is inside of a gap
+    pub ticks_until_gap_toggle: u32, // ticks until the current gap or the trail in between ends
+    pub active_effects: Vec<ActiveEffect>, // effects of picked up power-ups
+    #[serde(skip)]
+    pub pending_inputs: VecDeque<(u32, Direction)>, // inputs of the player that are applied one per tick
+    #[serde(skip)]
+    pub death_cause: Option<DeathCause>, // what the player crashed into in the current round
 }
 
 impl PlayerStates {
-    pub fn new(uuid: &str) -> Self {
+    pub fn new(uuid: &str, display_name: &str) -> Self {
         Self {
             id: uuid.to_string(),
+            display_name: display_name.to_string(),
+            color: PlayerColor::default(),
             position_x: 0.0,
             position_y: 0.0,
             direction: 0.0,
             is_alive: true,
             trail: Vec::new(),
             current_direction: Direction::Straight,
+            trail_grid: TrailGrid::default(),
+            last_processed_input: 0,
+            is_drawing_trail: true,
+            ticks_until_gap_toggle: 0,
+            active_effects: Vec::new(),
+            pending_inputs: VecDeque::new(),
+            death_cause: None,
+        }
+    }
+
+    pub fn collides_with_own_trail(&mut self, settings: &GameSettings) {
+        if self.collide_with_trail_of(
+            self,
+            self.ignored_newest_own_trail_points(settings),
+            settings,
+        ) {
+            self.kill(DeathCause::OwnTrail);
         }
     }
 
-    pub fn collides_with_own_trail(&mut self) -> () {
-        if self.collide_with_trail_collection(&self.trail) {
-            self.is_alive = false;
+    pub fn collides_with_other_player(
+        &mut self,
+        other_player: &PlayerStates,
+        settings: &GameSettings,
+    ) {
+        if self.collide_with_trail_of(other_player, 0, settings) {
+            self.kill(DeathCause::PlayerTrail(other_player.id.clone()));
         }
     }
 
-    pub fn collides_with_other_player(&mut self, other_player: &PlayerStates) -> () {
-        if self.collide_with_trail_collection(&other_player.trail) {
-            self.is_alive = false;
+    /// Only the first thing the player crashed into counts
+    pub fn kill(&mut self, cause: DeathCause) {
+        self.is_alive = false;
+        if self.death_cause.is_none() {
+            self.death_cause = Some(cause);
         }
     }
 
-    pub fn set_direction(&mut self, direction: Direction) -> () {
+    pub fn is_outside_of_board(&self, settings: &GameSettings) -> bool {
+        self.position_x > (settings.board_width / 2.0)
+            || self.position_x < -(settings.board_width / 2.0)
+            || self.position_y > (settings.board_height / 2.0)
+            || self.position_y < -(settings.board_height / 2.0)
+    }
+
+    /// Checks if the player would crash at his current position - into a wall or any of the trails of the given players.
+    /// Our own trail is looked up by our id, so it also works for copies of the player without a trail.
+    pub fn would_crash(&self, players: &[PlayerStates], settings: &GameSettings) -> bool {
+        if self.is_outside_of_board(settings) {
+            return true;
+        }
+
+        players.iter().any(|other_player| {
+            let ignore_newest = if other_player.id == self.id {
+                other_player.ignored_newest_own_trail_points(settings)
+            } else {
+                0
+            };
+            self.collide_with_trail_of(other_player, ignore_newest, settings)
+        })
+    }
+
+    /// The newest trail points of a player are always right behind him - he would collide with them immediately.
+    /// All of them up to the first one that is out of his reach are left out, so it does not matter how fast he was.
+    pub fn ignored_newest_own_trail_points(&self, settings: &GameSettings) -> usize {
+        self.trail
+            .iter()
+            .rev()
+            .position(|point| {
+                let dx = point.x - self.position_x;
+                let dy = point.y - self.position_y;
+                (dx * dx + dy * dy).sqrt() >= settings.collision_radius
+            })
+            .unwrap_or(self.trail.len())
+    }
+
+    pub fn set_direction(&mut self, direction: Direction) {
         self.current_direction = direction;
     }
 
-    pub fn collide_with_trail_collection(&self, trail_collection: &Vec<(f32, f32)>) -> bool {
-        // this is not a very efficient way of checking for collision
-        // if having multiple players with lots of trails are causing lags on the server
-        // trails could be divided into partitions that are orienting on the gameboard
-        // then you would only have to check the player against trails in his current partition and the ones around him
-        for (px, py) in trail_collection {
-            let dx = px - self.position_x;
-            let dy = py - self.position_y;
+    /// First part of every tick: the effects run out, the input is taken over and the player turns
+    pub fn begin_tick(&mut self, input: Option<(u32, Direction)>, settings: &GameSettings) {
+        self.tick_effects();
+        if let Some((sequence, direction)) = input {
+            self.apply_input(sequence, direction);
+        }
+        self.steer_player(settings);
+    }
+
+    /// Buffers an input of the player - it will be applied with one of the next ticks
+    pub fn queue_input(&mut self, sequence: u32, direction: Direction) {
+        self.pending_inputs.push_back((sequence, direction));
+
+        // the dropped inputs were never simulated, so they must not count as processed -
+        // the client replays them until the next applied input confirms a newer sequence
+        while self.pending_inputs.len() > MAX_BUFFERED_INPUTS {
+            self.pending_inputs.pop_front();
+        }
+    }
+
+    /// Takes the oldest buffered input out of the buffer - it is applied with the next tick
+    pub fn next_input(&mut self) -> Option<(u32, Direction)> {
+        self.pending_inputs.pop_front()
+    }
 
-            if (dx * dx + dy * dy).sqrt() < COLLISION_RADIUS {
+    pub fn apply_input(&mut self, sequence: u32, direction: Direction) {
+        self.last_processed_input = sequence;
+        self.set_direction(direction);
+    }
+
+    /// Checks if we collide with the trail of the given player, leaving out his newest trail points.
+    /// Only the trail points in the partitions around our position are looked at.
+    pub fn collide_with_trail_of(
+        &self,
+        other_player: &PlayerStates,
+        ignore_newest: usize,
+        settings: &GameSettings,
+    ) -> bool {
+        let checked_points = other_player.trail.len().saturating_sub(ignore_newest);
+
+        for index in other_player
+            .trail_grid
+            .nearby(self.position_x, self.position_y)
+        {
+            if index >= checked_points {
+                continue;
+            }
+
+            let point = other_player.trail[index];
+            let dx = point.x - self.position_x;
+            let dy = point.y - self.position_y;
+
+            let collision_radius = if point.is_thin {
+                settings.thin_trail_collision_radius()
+            } else {
+                settings.collision_radius
+            };
+
+            if (dx * dx + dy * dy).sqrt() < collision_radius {
                 return true;
             }
         }
@@ -69,25 +221,134 @@ impl PlayerStates {
         false
     }
 
-    pub fn steer_player(&mut self) -> () {
+    pub fn steer_player(&mut self, settings: &GameSettings) {
         match self.current_direction {
             Direction::Left => {
-                self.direction += ROTATION_SPEED_PER_TICK;
-                self.direction = self.direction % (PI * 2.0);
+                self.direction += settings.rotation_speed_per_tick();
+                self.direction %= PI * 2.0;
             }
             Direction::Right => {
-                self.direction -= ROTATION_SPEED_PER_TICK;
-                self.direction = self.direction % (PI * 2.0);
+                self.direction -= settings.rotation_speed_per_tick();
+                self.direction %= PI * 2.0;
             }
             Direction::Straight => {}
         };
     }
 
-    pub fn move_player(&mut self) -> () {
-        self.trail.push((self.position_x, self.position_y));
+    /// Copy of the player without his trail - used for sending the state to the clients
+    pub fn without_trail(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            display_name: self.display_name.clone(),
+            color: self.color,
+            position_x: self.position_x,
+            position_y: self.position_y,
+            direction: self.direction,
+            is_alive: self.is_alive,
+            trail: Vec::new(),
+            current_direction: self.current_direction.clone(),
+            trail_grid: TrailGrid::default(),
+            last_processed_input: self.last_processed_input,
+            is_drawing_trail: self.is_drawing_trail,
+            ticks_until_gap_toggle: self.ticks_until_gap_toggle,
+            active_effects: self.active_effects.clone(),
+            pending_inputs: VecDeque::new(),
+            death_cause: self.death_cause.clone(),
+        }
+    }
+
+    /// Players inside of a gap and invincible players slip through all trails
+    pub fn can_collide_with_trails(&self) -> bool {
+        self.is_drawing_trail && !self.has_effect(EffectKind::Invincible)
+    }
+
+    pub fn has_effect(&self, kind: EffectKind) -> bool {
+        self.active_effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// Activates the effect - if it is already active, it starts over
+    pub fn add_effect(&mut self, kind: EffectKind, ticks: u32) {
+        self.active_effects.retain(|effect| effect.kind != kind);
+        self.active_effects.push(ActiveEffect {
+            kind,
+            ticks_remaining: ticks,
+        });
+    }
+
+    /// Counts down all active effects and removes the ones that ran out
+    pub fn tick_effects(&mut self) {
+        for effect in self.active_effects.iter_mut() {
+            effect.ticks_remaining = effect.ticks_remaining.saturating_sub(1);
+        }
+        self.active_effects
+            .retain(|effect| effect.ticks_remaining > 0);
+    }
+
+    fn speed_factor(&self) -> f32 {
+        self.active_effects
+            .iter()
+            .map(|effect| effect.kind.speed_factor())
+            .product()
+    }
+
+    /// Takes over the trail points of a trail update - the points from the start index on are replaced
+    pub fn update_trail(
+        &mut self,
+        start_index: usize,
+        points: &[TrailPoint],
+        settings: &GameSettings,
+    ) {
+        while self.trail.len() > start_index {
+            if let Some(point) = self.trail.pop() {
+                self.trail_grid.remove_newest(point.x, point.y);
+            }
+        }
+
+        for point in points {
+            self.trail_grid
+                .insert(self.trail.len(), point.x, point.y, settings);
+            self.trail.push(*point);
+        }
+    }
+
+    pub fn reset_trail(&mut self) {
+        self.trail = Vec::new();
+        self.trail_grid.clear();
+    }
+
+    /// Counts down to the next start or end of a gap.
+    /// When a phase ends, `next_phase_ticks` decides how long the next one lasts - it is told if the next phase is a gap.
+    pub fn update_trail_gap(&mut self, next_phase_ticks: impl FnOnce(bool) -> u32) {
+        if self.ticks_until_gap_toggle > 0 {
+            self.ticks_until_gap_toggle -= 1;
+            return;
+        }
+
+        self.is_drawing_trail = !self.is_drawing_trail;
+        self.ticks_until_gap_toggle = next_phase_ticks(!self.is_drawing_trail);
+    }
+
+    /// Leaves a trail point at the current position (unless we are inside of a gap) and moves on
+    pub fn move_player(&mut self, settings: &GameSettings) {
+        if self.is_drawing_trail {
+            self.trail_grid
+                .insert(self.trail.len(), self.position_x, self.position_y, settings);
+            self.trail.push(TrailPoint {
+                x: self.position_x,
+                y: self.position_y,
+                is_thin: self.has_effect(EffectKind::ThinTrail),
+            });
+        }
+
+        self.advance_position(settings);
+    }
 
-        let dx = MOVE_SPEED_PER_TICK * self.direction.cos();
-        let dy = MOVE_SPEED_PER_TICK * self.direction.sin();
+    /// Moves the player one tick into his direction without leaving a trail point
+    pub fn advance_position(&mut self, settings: &GameSettings) {
+        let move_speed = settings.move_speed_per_tick() * self.speed_factor();
+        let (sin, cos) = sin_cos(self.direction);
+        let dx = move_speed * cos;
+        let dy = move_speed * sin;
 
         self.position_x += dx;
         self.position_y += dy;
@@ -98,12 +359,21 @@ impl Clone for PlayerStates {
     fn clone(&self) -> Self {
         Self {
             id: self.id.clone(),
-            position_x: self.position_x.clone(),
-            position_y: self.position_y.clone(),
-            direction: self.direction.clone(),
-            is_alive: self.is_alive.clone(),
+            display_name: self.display_name.clone(),
+            color: self.color,
+            position_x: self.position_x,
+            position_y: self.position_y,
+            direction: self.direction,
+            is_alive: self.is_alive,
             trail: self.trail.clone(),
             current_direction: self.current_direction.clone(),
+            trail_grid: self.trail_grid.clone(),
+            last_processed_input: self.last_processed_input,
+            is_drawing_trail: self.is_drawing_trail,
+            ticks_until_gap_toggle: self.ticks_until_gap_toggle,
+            active_effects: self.active_effects.clone(),
+            pending_inputs: self.pending_inputs.clone(),
+            death_cause: self.death_cause.clone(),
         }
     }
 }
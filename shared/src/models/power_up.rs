@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use super::game_settings::GameSettings;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum PowerUpKind {
+    SpeedBoost,    // the player picking it up gets faster
+    SlowOthers,    // all other players get slower
+    ThinTrail,     // the trail of the player gets thinner and is easier to slip past
+    Invincibility, // the player can not collide with trails
+    TrailErase,    // all trails on the board are erased
+}
+
+/// Power-up lying on the game-board until a player picks it up
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PowerUp {
+    pub id: u32,
+    pub kind: PowerUpKind,
+    pub position_x: f32,
+    pub position_y: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum EffectKind {
+    SpeedBoost,
+    Slowed,
+    ThinTrail,
+    Invincible,
+}
+
+/// Effect of a power-up that is currently active on a player
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ActiveEffect {
+    pub kind: EffectKind,
+    pub ticks_remaining: u32,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 5] = [
+        PowerUpKind::SpeedBoost,
+        PowerUpKind::SlowOthers,
+        PowerUpKind::ThinTrail,
+        PowerUpKind::Invincibility,
+        PowerUpKind::TrailErase,
+    ];
+
+    /// Players closer to the power-up than this pick it up
+    pub fn pickup_radius(&self) -> f32 {
+        25.0
+    }
+
+    /// How long the effect of the power-up lasts - erasing the trails happens at once
+    pub fn duration_ticks(&self, settings: &GameSettings) -> u32 {
+        match self {
+            PowerUpKind::SpeedBoost
+            | PowerUpKind::SlowOthers
+            | PowerUpKind::ThinTrail
+            | PowerUpKind::Invincibility => settings.ticks(5.0),
+            PowerUpKind::TrailErase => 0,
+        }
+    }
+}
+
+impl EffectKind {
+    /// Factor the move-speed of a player with this effect is multiplied with
+    pub fn speed_factor(&self) -> f32 {
+        match self {
+            EffectKind::SpeedBoost => 1.6,
+            EffectKind::Slowed => 0.6,
+            EffectKind::ThinTrail | EffectKind::Invincible => 1.0,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{game_settings::GameSettings, network_message::GameStateMessage};
+
+const REPLAY_FILE_EXTENSION: &str = "replay";
+
+// a whole match would be one huge message - it is sent to the clients in parts of this many frames
+const FRAMES_PER_CHUNK: usize = 200;
+
+/// Everything that happened in a match - the game-states of its ticks, so it can be played back afterwards
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Replay {
+    pub lobby_id: String,
+    pub settings: GameSettings,        // rules the match was played with
+    pub recorded_at_millis: u64,       // when the match started (millis since unix-epoch)
+    pub frames: Vec<GameStateMessage>, // the first game-state of every round is a keyframe
+}
+
+/// A few frames of a replay - the other fields are sent along with every chunk, so each one can be told apart
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReplayChunkMessage {
+    pub chunk_index: u32,
+    pub chunk_count: u32,
+    pub replay: Replay, // only contains the frames of this chunk
+}
+
+impl Replay {
+    /// Splits the frames into chunks that are small enough to be sent as single messages
+    pub fn chunks(&self) -> Vec<ReplayChunkMessage> {
+        let frame_chunks: Vec<&[GameStateMessage]> = self.frames.chunks(FRAMES_PER_CHUNK).collect();
+        let chunk_count = frame_chunks.len() as u32;
+
+        frame_chunks
+            .into_iter()
+            .enumerate()
+            .map(|(chunk_index, frames)| ReplayChunkMessage {
+                chunk_index: chunk_index as u32,
+                chunk_count,
+                replay: Replay {
+                    lobby_id: self.lobby_id.clone(),
+                    settings: self.settings,
+                    recorded_at_millis: self.recorded_at_millis,
+                    frames: frames.to_vec(),
+                },
+            })
+            .collect()
+    }
+
+    /// Appends the frames of the next chunk - it has to belong to this replay and come right after the last one.
+    /// Returns if the replay is complete now.
+    pub fn append_chunk(
+        &mut self,
+        expected_index: u32,
+        chunk: ReplayChunkMessage,
+    ) -> Result<bool, String> {
+        if chunk.replay.lobby_id != self.lobby_id
+            || chunk.replay.recorded_at_millis != self.recorded_at_millis
+        {
+            return Err("Chunk belongs to another replay".to_string());
+        }
+        if chunk.chunk_index != expected_index {
+            return Err(format!(
+                "Expected chunk {} but got {}",
+                expected_index, chunk.chunk_index
+            ));
+        }
+
+        self.frames.extend(chunk.replay.frames);
+        Ok(chunk.chunk_index + 1 >= chunk.chunk_count)
+    }
+
+    pub fn first_tick(&self) -> u64 {
+        self.frames.first().map_or(0, |frame| frame.tick)
+    }
+
+    pub fn last_tick(&self) -> u64 {
+        self.frames.last().map_or(0, |frame| frame.tick)
+    }
+
+    /// Index of the newest keyframe at or before the given tick - playing back from there restores the tick
+    pub fn keyframe_index_before(&self, tick: u64) -> usize {
+        self.frames
+            .iter()
+            .rposition(|frame| frame.is_keyframe && frame.tick <= tick)
+            .unwrap_or(0)
+    }
+
+    /// Writes the replay as MessagePack into the given directory and returns the path of the file
+    pub fn save(&self, directory: &Path) -> Result<PathBuf, String> {
+        fs::create_dir_all(directory)
+            .map_err(|err| format!("Failed creating {}: {}", directory.display(), err))?;
+
+        let path = directory.join(format!(
+            "{}-{}.{}",
+            self.lobby_id, self.recorded_at_millis, REPLAY_FILE_EXTENSION
+        ));
+        let bytes =
+            rmp_serde::to_vec(self).map_err(|err| format!("Failed serializing: {}", err))?;
+        fs::write(&path, bytes)
+            .map_err(|err| format!("Failed writing {}: {}", path.display(), err))?;
+
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|err| format!("Failed reading {}: {}", path.display(), err))?;
+        rmp_serde::from_slice(&bytes).map_err(|err| format!("Failed deserializing: {}", err))
+    }
+
+    /// The replay inside of the directory that was written last
+    pub fn newest_in(directory: &Path) -> Option<PathBuf> {
+        fs::read_dir(directory)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .path()
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    == Some(REPLAY_FILE_EXTENSION)
+            })
+            .max_by_key(|entry| {
+                entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .map(|entry| entry.path())
+    }
+}
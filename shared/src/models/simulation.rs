@@ -0,0 +1,302 @@
+use std::f32::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    direction::Direction,
+    game_settings::GameSettings,
+    player_states::{DeathCause, PlayerStates},
+    power_up::{EffectKind, PowerUp, PowerUpKind},
+    simulation_rng::SimulationRng,
+    MAX_GAP_SECONDS, MAX_POWER_UPS_ON_BOARD, MAX_SECONDS_BETWEEN_GAPS,
+    MAX_SECONDS_BETWEEN_POWER_UPS, MIN_GAP_SECONDS, MIN_SECONDS_BETWEEN_GAPS,
+    MIN_SECONDS_BETWEEN_POWER_UPS,
+};
+
+/// Input of a single player that is applied with the next tick
+#[derive(Clone)]
+pub struct PlayerInput {
+    pub player_id: String,
+    pub sequence: u32,
+    pub direction: Direction,
+}
+
+/// Everything the rules of a round depend on.
+/// Starting from the same seed, stepping it with the same inputs always ends up in the same state -
+/// the backend and the simulator run it, the clients step it ahead with their own inputs.
+#[derive(Clone, Default)]
+pub struct SimulationState {
+    pub players: Vec<PlayerStates>,
+    pub power_ups: Vec<PowerUp>,
+    pub next_power_up_id: u32,
+    pub ticks_until_power_up: u32,
+    pub seed: u64, // seed of the current match
+    pub rng: SimulationRng,
+    pub settings: GameSettings, // can only be changed between matches
+}
+
+/// The part of the simulation that can not be seen on the board - it is sent along with every game-state,
+/// so the clients can put the simulation together and step it the same way the backend does
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SimulationSync {
+    pub seed: u64,
+    pub rng: SimulationRng,
+    pub next_power_up_id: u32,
+    pub ticks_until_power_up: u32,
+}
+
+impl SimulationState {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: SimulationRng::new(seed),
+            ..Default::default()
+        }
+    }
+
+    /// Puts the simulation back together from a game-state - the players need their trails for the collision-checks
+    pub fn from_sync(
+        players: Vec<PlayerStates>,
+        power_ups: Vec<PowerUp>,
+        sync: SimulationSync,
+        settings: GameSettings,
+    ) -> Self {
+        Self {
+            players,
+            power_ups,
+            next_power_up_id: sync.next_power_up_id,
+            ticks_until_power_up: sync.ticks_until_power_up,
+            seed: sync.seed,
+            rng: sync.rng,
+            settings,
+        }
+    }
+
+    pub fn sync(&self) -> SimulationSync {
+        SimulationSync {
+            seed: self.seed,
+            rng: self.rng.clone(),
+            next_power_up_id: self.next_power_up_id,
+            ticks_until_power_up: self.ticks_until_power_up,
+        }
+    }
+
+    /// Starts a new match with the given seed - the players stay
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = SimulationRng::new(seed);
+        self.next_power_up_id = 0;
+    }
+
+    /// Places all players on random positions with random directions and clears the board
+    pub fn start_round(&mut self) {
+        let rng = &mut self.rng;
+        let settings = &self.settings;
+
+        for player in self.players.iter_mut() {
+            (player.position_x, player.position_y) = random_position(rng, settings);
+            player.direction = rng.range_f32(0.0, 2.0 * PI);
+
+            // reset player state
+            player.is_alive = true;
+            player.current_direction = Direction::Straight;
+            player.pending_inputs.clear();
+            player.active_effects.clear();
+            player.death_cause = None;
+            player.reset_trail();
+
+            // nobody starts with a gap - the first one comes at a random time
+            player.is_drawing_trail = true;
+            player.ticks_until_gap_toggle = random_ticks(
+                rng,
+                settings,
+                MIN_SECONDS_BETWEEN_GAPS,
+                MAX_SECONDS_BETWEEN_GAPS,
+            );
+        }
+
+        // the board starts without power-ups
+        self.power_ups.clear();
+        self.ticks_until_power_up = random_ticks(
+            rng,
+            settings,
+            MIN_SECONDS_BETWEEN_POWER_UPS,
+            MAX_SECONDS_BETWEEN_POWER_UPS,
+        );
+    }
+}
+
+/// Moves the state one tick further - players without an input keep their direction
+pub fn simulate(mut state: SimulationState, inputs: &[PlayerInput]) -> SimulationState {
+    let rng = &mut state.rng;
+    let settings = &state.settings;
+
+    for player in state.players.iter_mut() {
+        if player.is_alive {
+            let input = inputs
+                .iter()
+                .find(|input| input.player_id == player.id)
+                .map(|input| (input.sequence, input.direction.clone()));
+            player.begin_tick(input, settings);
+
+            // start or end gaps in the trail at random times
+            player.update_trail_gap(|is_gap| {
+                if is_gap {
+                    random_ticks(rng, settings, MIN_GAP_SECONDS, MAX_GAP_SECONDS)
+                } else {
+                    random_ticks(
+                        rng,
+                        settings,
+                        MIN_SECONDS_BETWEEN_GAPS,
+                        MAX_SECONDS_BETWEEN_GAPS,
+                    )
+                }
+            });
+
+            player.move_player(settings);
+        }
+    }
+
+    // power-ups are spawned and picked up before anyone can collide
+    spawn_power_ups(&mut state);
+    pick_up_power_ups(&mut state);
+    check_collision(&mut state.players, &state.settings);
+
+    state
+}
+
+/// Every living player is checked against every trail - the trails of dead players stay on the board,
+/// so who crashes into them must not depend on the order the players joined in
+fn check_collision(players: &mut [PlayerStates], settings: &GameSettings) {
+    for i in 0..players.len() {
+        let (players_before, players_from) = players.split_at_mut(i);
+        let Some((player, players_after)) = players_from.split_first_mut() else {
+            continue;
+        };
+
+        if !player.is_alive {
+            continue;
+        }
+
+        // check if we collide with our own trail - players inside of a gap or invincible ones slip through all trails
+        if player.can_collide_with_trails() {
+            player.collides_with_own_trail(settings);
+        }
+
+        // check if we are out of bounds
+        if player.is_outside_of_board(settings) {
+            player.kill(DeathCause::Wall);
+        }
+
+        if player.can_collide_with_trails() {
+            for other_player in players_before.iter().chain(players_after.iter()) {
+                player.collides_with_other_player(other_player, settings);
+            }
+        }
+    }
+}
+
+/// Counts down to the next power-up and places it on a random position on the board
+fn spawn_power_ups(state: &mut SimulationState) {
+    if state.ticks_until_power_up > 0 {
+        state.ticks_until_power_up -= 1;
+        return;
+    }
+
+    state.ticks_until_power_up = random_ticks(
+        &mut state.rng,
+        &state.settings,
+        MIN_SECONDS_BETWEEN_POWER_UPS,
+        MAX_SECONDS_BETWEEN_POWER_UPS,
+    );
+    if state.power_ups.len() >= MAX_POWER_UPS_ON_BOARD {
+        return;
+    }
+
+    let kind = PowerUpKind::ALL[state.rng.index(PowerUpKind::ALL.len())];
+    let (position_x, position_y) = random_position(&mut state.rng, &state.settings);
+
+    state.next_power_up_id += 1;
+    state.power_ups.push(PowerUp {
+        id: state.next_power_up_id,
+        kind,
+        position_x,
+        position_y,
+    });
+}
+
+/// Every alive player that drives over a power-up picks it up
+fn pick_up_power_ups(state: &mut SimulationState) {
+    let mut picked_up = Vec::new();
+    let players = &state.players;
+
+    state.power_ups.retain(|power_up| {
+        let picking_player = players.iter().position(|p| {
+            let dx = p.position_x - power_up.position_x;
+            let dy = p.position_y - power_up.position_y;
+            p.is_alive && (dx * dx + dy * dy).sqrt() < power_up.kind.pickup_radius()
+        });
+
+        match picking_player {
+            Some(player_index) => {
+                picked_up.push((player_index, power_up.kind));
+                false
+            }
+            None => true,
+        }
+    });
+
+    for (player_index, kind) in picked_up {
+        apply_power_up(&mut state.players, player_index, kind, &state.settings);
+    }
+}
+
+fn apply_power_up(
+    players: &mut [PlayerStates],
+    player_index: usize,
+    kind: PowerUpKind,
+    settings: &GameSettings,
+) {
+    let effect_ticks = kind.duration_ticks(settings);
+    match kind {
+        PowerUpKind::SpeedBoost => {
+            players[player_index].add_effect(EffectKind::SpeedBoost, effect_ticks)
+        }
+        PowerUpKind::ThinTrail => {
+            players[player_index].add_effect(EffectKind::ThinTrail, effect_ticks)
+        }
+        PowerUpKind::Invincibility => {
+            players[player_index].add_effect(EffectKind::Invincible, effect_ticks)
+        }
+        PowerUpKind::SlowOthers => {
+            for (index, player) in players.iter_mut().enumerate() {
+                if index != player_index && player.is_alive {
+                    player.add_effect(EffectKind::Slowed, effect_ticks);
+                }
+            }
+        }
+        PowerUpKind::TrailErase => {
+            for player in players.iter_mut() {
+                player.reset_trail();
+            }
+        }
+    }
+}
+
+/// Random position on the board that keeps some distance to the walls
+fn random_position(rng: &mut SimulationRng, settings: &GameSettings) -> (f32, f32) {
+    let x_max = (settings.board_width / 2.0) - settings.unspawnable_edge;
+    let y_max = (settings.board_height / 2.0) - settings.unspawnable_edge;
+
+    (rng.range_f32(-x_max, x_max), rng.range_f32(-y_max, y_max))
+}
+
+/// Random number of ticks for a duration between the given seconds - it lasts as long with every tick rate
+fn random_ticks(
+    rng: &mut SimulationRng,
+    settings: &GameSettings,
+    min_seconds: f32,
+    max_seconds: f32,
+) -> u32 {
+    rng.range_u32(settings.ticks(min_seconds), settings.ticks(max_seconds))
+}
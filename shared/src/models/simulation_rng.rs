@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// Small random generator (SplitMix64) - it gives the same numbers for the same seed on every platform
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub struct SimulationRng {
+    state: u64,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Number between min and max - both included
+    pub fn range_u32(&mut self, min: u32, max: u32) -> u32 {
+        let span = (max - min) as u64 + 1;
+        min + (self.next_u64() % span) as u32
+    }
+
+    /// Index into a list of the given length
+    pub fn index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+
+    /// True with the given probability (between 0 and 1)
+    pub fn chance(&mut self, probability: f64) -> bool {
+        // 53 random bits fit exactly into the mantissa of a f64
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < probability
+    }
+
+    /// Number between min (included) and max (excluded)
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        // 24 random bits fit exactly into the mantissa of a f32
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+}
@@ -0,0 +1,75 @@
+use super::game_settings::GameSettings;
+
+/// Uniform grid over the game-board that remembers which trail points are lying inside each cell.
+/// Instead of checking every single trail point, a collision check only has to look at the neighbouring cells.
+#[derive(Clone, Default)]
+pub struct TrailGrid {
+    cells: Vec<Vec<usize>>, // indices into the trail - allocated with the first insert
+    columns: usize,
+    rows: usize,
+    cell_size: f32, // a cell is as big as the collision-radius, so every possible collision lies in the cell of the player or one around it
+    half_width: f32,
+    half_height: f32,
+}
+
+impl TrailGrid {
+    /// Remembers the trail point with the given index at its position
+    pub fn insert(&mut self, index: usize, x: f32, y: f32, settings: &GameSettings) {
+        if self.cells.is_empty() {
+            self.cell_size = settings.collision_radius;
+            self.half_width = settings.board_width / 2.0;
+            self.half_height = settings.board_height / 2.0;
+            self.columns = (settings.board_width / self.cell_size) as usize + 1;
+            self.rows = (settings.board_height / self.cell_size) as usize + 1;
+            self.cells = vec![Vec::new(); self.columns * self.rows];
+        }
+
+        let (column, row) = self.cell_of(x, y);
+        self.cells[row * self.columns + column].push(index);
+    }
+
+    /// Forgets the newest trail point at the given position - it was the last one put into its cell
+    pub fn remove_newest(&mut self, x: f32, y: f32) {
+        let (column, row) = self.cell_of(x, y);
+        if let Some(cell) = self.cells.get_mut(row * self.columns + column) {
+            cell.pop();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells = Vec::new();
+    }
+
+    /// Returns the indices of all trail points in the cell of the position and the eight cells around it
+    pub fn nearby(&self, x: f32, y: f32) -> impl Iterator<Item = usize> + '_ {
+        let (column, row) = self.cell_of(x, y);
+
+        // as long as nothing was inserted there are no cells - and nothing is found
+        let columns = column.saturating_sub(1)..=(column + 1).min(self.columns.saturating_sub(1));
+        let rows = row.saturating_sub(1)..=(row + 1).min(self.rows.saturating_sub(1));
+
+        rows.flat_map(move |row| {
+            columns
+                .clone()
+                .map(move |column| row * self.columns + column)
+        })
+        .filter_map(|cell| self.cells.get(cell))
+        .flatten()
+        .copied()
+    }
+
+    // positions outside of the board are put into the cells at the edge
+    fn cell_of(&self, x: f32, y: f32) -> (usize, usize) {
+        if self.cells.is_empty() {
+            return (0, 0);
+        }
+
+        let column = ((x + self.half_width) / self.cell_size).floor();
+        let row = ((y + self.half_height) / self.cell_size).floor();
+
+        (
+            column.clamp(0.0, (self.columns - 1) as f32) as usize,
+            row.clamp(0.0, (self.rows - 1) as f32) as usize,
+        )
+    }
+}
@@ -0,0 +1,90 @@
+use super::network_message::NetworkMessage;
+
+/// Format the network-messages are encoded with.
+/// JSON is the default, so the backend can still be debugged with tools like Postman.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WireFormat {
+    Json,        // sent as websocket text-frames
+    MessagePack, // sent as websocket binary-frames
+}
+
+/// A network-message that is ready to be sent over the websocket
+pub enum EncodedMessage {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+// the format is negotiated with a query parameter when connecting, e.g. ws://localhost:11255/?format=msgpack
+const FORMAT_QUERY_PARAMETER: &str = "format";
+const MESSAGE_PACK_QUERY_VALUE: &str = "msgpack";
+// a player that lost his connection takes his old slot back with the token he got, e.g. ?resume=<token>
+const RESUME_QUERY_PARAMETER: &str = "resume";
+
+impl WireFormat {
+    /// Reads the format out of the query of the websocket-url - falls back to JSON
+    pub fn from_query(query: Option<&str>) -> Self {
+        if query_parameter(query, FORMAT_QUERY_PARAMETER) == Some(MESSAGE_PACK_QUERY_VALUE) {
+            WireFormat::MessagePack
+        } else {
+            WireFormat::Json
+        }
+    }
+
+    /// Query that has to be appended to the websocket-url to request this format.
+    /// With a resume-token the backend gives us back the player we had before losing the connection.
+    pub fn to_query(&self, resume_token: Option<&str>) -> String {
+        let mut parameters = Vec::new();
+        if *self == WireFormat::MessagePack {
+            parameters.push(format!(
+                "{}={}",
+                FORMAT_QUERY_PARAMETER, MESSAGE_PACK_QUERY_VALUE
+            ));
+        }
+        if let Some(resume_token) = resume_token {
+            parameters.push(format!("{}={}", RESUME_QUERY_PARAMETER, resume_token));
+        }
+
+        if parameters.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", parameters.join("&"))
+        }
+    }
+}
+
+/// Reads the resume-token out of the query of the websocket-url
+pub fn resume_token_from_query(query: Option<&str>) -> Option<String> {
+    query_parameter(query, RESUME_QUERY_PARAMETER).map(|token| token.to_string())
+}
+
+fn query_parameter<'a>(query: Option<&'a str>, key: &str) -> Option<&'a str> {
+    query
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(parameter_key, _)| *parameter_key == key)
+        .map(|(_, value)| value)
+}
+
+impl NetworkMessage {
+    pub fn encode(&self, wire_format: WireFormat) -> Result<EncodedMessage, String> {
+        match wire_format {
+            WireFormat::Json => serde_json::to_string(self)
+                .map(EncodedMessage::Text)
+                .map_err(|err| format!("Failed serializing: {}", err)),
+            WireFormat::MessagePack => rmp_serde::to_vec(self)
+                .map(EncodedMessage::Binary)
+                .map_err(|err| format!("Failed serializing: {}", err)),
+        }
+    }
+
+    /// Decodes a message that was received as text-frame
+    pub fn decode_text(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|err| format!("Failed deserializing: {}", err))
+    }
+
+    /// Decodes a message that was received as binary-frame
+    pub fn decode_binary(bytes: &[u8]) -> Result<Self, String> {
+        rmp_serde::from_slice(bytes).map_err(|err| format!("Failed deserializing: {}", err))
+    }
+}
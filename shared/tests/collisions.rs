@@ -0,0 +1,51 @@
+use shared::models::{
+    game_settings::GameSettings,
+    player_states::{DeathCause, PlayerStates, TrailPoint},
+    simulation::{simulate, SimulationState},
+};
+
+// the dead player left a trail straight through the middle of the board
+fn dead_player(settings: &GameSettings) -> PlayerStates {
+    let mut player = PlayerStates::new("dead", "dead");
+    let trail: Vec<TrailPoint> = (-10..=10)
+        .map(|index| TrailPoint {
+            x: 0.0,
+            y: index as f32 * 10.0,
+            is_thin: false,
+        })
+        .collect();
+    player.update_trail(0, &trail, settings);
+    player.kill(DeathCause::Wall);
+    player
+}
+
+// the living player moves right into the trail of the dead one
+fn living_player() -> PlayerStates {
+    let mut player = PlayerStates::new("alive", "alive");
+    player.position_x = -10.0;
+    player.ticks_until_gap_toggle = 1000;
+    player
+}
+
+fn crashes_into_dead_trail(dead_player_joined_first: bool) -> bool {
+    let mut state = SimulationState::new(7);
+    state.ticks_until_power_up = 1000;
+
+    let dead_player = dead_player(&state.settings);
+    state.players = if dead_player_joined_first {
+        vec![dead_player, living_player()]
+    } else {
+        vec![living_player(), dead_player]
+    };
+
+    let state = simulate(state, &[]);
+    let player = state.players.iter().find(|p| p.id == "alive").unwrap();
+    !player.is_alive && player.death_cause == Some(DeathCause::PlayerTrail("dead".to_string()))
+}
+
+// the trails of dead players stay on the board - no matter who joined first
+#[test]
+fn trail_of_dead_player_kills_regardless_of_join_order() {
+    assert!(crashes_into_dead_trail(true));
+    assert!(crashes_into_dead_trail(false));
+}
@@ -0,0 +1,175 @@
+use shared::models::{
+    deterministic_math::sin_cos,
+    direction::Direction,
+    player_states::PlayerStates,
+    simulation::{simulate, PlayerInput, SimulationState, SimulationSync},
+    simulation_rng::SimulationRng,
+};
+
+const PLAYER_COUNT: usize = 4;
+const TICKS: u32 = 1500;
+
+// the game-state the client gets - early enough that every player is still alive
+const GAME_STATE_TICK: u32 = 50;
+
+fn start_match(seed: u64) -> SimulationState {
+    let mut state = SimulationState::new(seed);
+    for index in 0..PLAYER_COUNT {
+        let id = format!("player-{}", index);
+        state.players.push(PlayerStates::new(&id, &id));
+    }
+    state.start_round();
+    state
+}
+
+// every player changes his direction from time to time - the same way in every run
+fn scripted_inputs(tick: u32) -> Vec<PlayerInput> {
+    (0..PLAYER_COUNT)
+        .map(|index| PlayerInput {
+            player_id: format!("player-{}", index),
+            sequence: tick,
+            direction: match (tick / 25 + index as u32) % 3 {
+                0 => Direction::Left,
+                1 => Direction::Right,
+                _ => Direction::Straight,
+            },
+        })
+        .collect()
+}
+
+fn play(seed: u64) -> SimulationState {
+    let mut state = start_match(seed);
+    for tick in 1..=TICKS {
+        state = simulate(state, &scripted_inputs(tick));
+    }
+    state
+}
+
+// everything the outcome of a round depends on - floats are compared by their bits
+fn fingerprint(state: &SimulationState) -> Vec<u64> {
+    let mut fingerprint = Vec::new();
+
+    for player in state.players.iter() {
+        fingerprint.push(player.position_x.to_bits() as u64);
+        fingerprint.push(player.position_y.to_bits() as u64);
+        fingerprint.push(player.direction.to_bits() as u64);
+        fingerprint.push(player.is_alive as u64);
+        fingerprint.push(player.is_drawing_trail as u64);
+        fingerprint.push(player.last_processed_input as u64);
+        fingerprint.push(player.trail.len() as u64);
+        for point in player.trail.iter() {
+            fingerprint.push(point.x.to_bits() as u64);
+            fingerprint.push(point.y.to_bits() as u64);
+        }
+    }
+
+    for power_up in state.power_ups.iter() {
+        fingerprint.push(power_up.id as u64);
+        fingerprint.push(power_up.position_x.to_bits() as u64);
+        fingerprint.push(power_up.position_y.to_bits() as u64);
+    }
+
+    fingerprint.push(state.rng.clone().next_u64());
+    fingerprint
+}
+
+#[test]
+fn same_seed_and_inputs_end_in_the_same_state() {
+    for seed in [0, 1, 42, u64::MAX] {
+        assert_eq!(fingerprint(&play(seed)), fingerprint(&play(seed)));
+    }
+}
+
+#[test]
+fn different_seeds_start_differently() {
+    assert_ne!(fingerprint(&start_match(1)), fingerprint(&start_match(2)));
+}
+
+#[test]
+fn every_round_of_a_match_starts_differently() {
+    let mut state = start_match(7);
+    let first_round = fingerprint(&state);
+
+    state.start_round();
+    assert_ne!(first_round, fingerprint(&state));
+}
+
+#[test]
+fn inputs_are_applied() {
+    let state = play(3);
+    assert!(state
+        .players
+        .iter()
+        .filter(|p| p.is_alive)
+        .all(|p| p.last_processed_input == TICKS));
+
+    let straight_inputs: Vec<PlayerInput> = (0..PLAYER_COUNT)
+        .map(|index| PlayerInput {
+            player_id: format!("player-{}", index),
+            sequence: 1,
+            direction: Direction::Straight,
+        })
+        .collect();
+    let steered = simulate(start_match(3), &scripted_inputs(1));
+    let straight = simulate(start_match(3), &straight_inputs);
+    assert_ne!(fingerprint(&steered), fingerprint(&straight));
+}
+
+// the client only gets what a game-state contains - put together, it has to step exactly like the backend
+#[test]
+fn simulation_from_a_game_state_steps_the_same_way() {
+    let mut backend = start_match(11);
+    for tick in 1..=GAME_STATE_TICK {
+        backend = simulate(backend, &scripted_inputs(tick));
+    }
+
+    let players: Vec<PlayerStates> = backend
+        .players
+        .iter()
+        .map(|player| {
+            let bytes = rmp_serde::to_vec(&player.without_trail()).unwrap();
+            let mut received: PlayerStates = rmp_serde::from_slice(&bytes).unwrap();
+            received.update_trail(0, &player.trail, &backend.settings);
+            received
+        })
+        .collect();
+    let sync: SimulationSync =
+        rmp_serde::from_slice(&rmp_serde::to_vec(&backend.sync()).unwrap()).unwrap();
+    let mut client =
+        SimulationState::from_sync(players, backend.power_ups.clone(), sync, backend.settings);
+
+    for tick in GAME_STATE_TICK + 1..=TICKS {
+        backend = simulate(backend, &scripted_inputs(tick));
+        client = simulate(client, &scripted_inputs(tick));
+    }
+    assert_eq!(fingerprint(&backend), fingerprint(&client));
+}
+
+#[test]
+fn rng_repeats_its_numbers_for_the_same_seed() {
+    let mut first = SimulationRng::new(1234);
+    let mut second = SimulationRng::new(1234);
+
+    for _ in 0..1000 {
+        assert_eq!(first.next_u64(), second.next_u64());
+
+        let value = first.range_u32(10, 20);
+        assert_eq!(value, second.range_u32(10, 20));
+        assert!((10..=20).contains(&value));
+
+        let value = first.range_f32(-5.0, 5.0);
+        assert_eq!(value.to_bits(), second.range_f32(-5.0, 5.0).to_bits());
+        assert!((-5.0..5.0).contains(&value));
+    }
+}
+
+#[test]
+fn sin_cos_is_close_to_the_platform() {
+    let mut angle = -20.0_f32;
+    while angle < 20.0 {
+        let (sin, cos) = sin_cos(angle);
+        assert!((sin - angle.sin()).abs() < 1e-5, "sin({})", angle);
+        assert!((cos - angle.cos()).abs() < 1e-5, "cos({})", angle);
+        angle += 0.01;
+    }
+}
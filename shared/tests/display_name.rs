@@ -0,0 +1,22 @@
+use shared::models::display_name::{
+    unique_display_name, validate_display_name, MAX_DISPLAY_NAME_LENGTH,
+};
+
+#[test]
+fn numbered_name_is_still_valid() {
+    let unique_name = unique_display_name("Bot", &["Bot", "Bot 2"]);
+
+    assert_eq!(unique_name, "Bot 3");
+    assert_eq!(validate_display_name(&unique_name), Ok(unique_name.clone()));
+}
+
+// a name with the maximum length has to be shortened to make room for the number
+#[test]
+fn numbered_name_stays_within_the_maximum_length() {
+    let display_name = "A".repeat(MAX_DISPLAY_NAME_LENGTH);
+    let unique_name = unique_display_name(&display_name, &[&display_name]);
+
+    assert_eq!(unique_name.chars().count(), MAX_DISPLAY_NAME_LENGTH);
+    assert!(unique_name.ends_with(" 2"));
+    assert_eq!(validate_display_name(&unique_name), Ok(unique_name.clone()));
+}
@@ -0,0 +1,14 @@
+use shared::models::{direction::Direction, player_states::PlayerStates};
+
+// inputs that do not fit into the buffer anymore are thrown away - they were never simulated
+#[test]
+fn dropped_inputs_are_not_processed() {
+    let mut player = PlayerStates::new("player", "player");
+    for sequence in 1..=15 {
+        player.queue_input(sequence, Direction::Left);
+    }
+
+    assert_eq!(player.last_processed_input, 0);
+    assert!(player.current_direction == Direction::Straight);
+    assert_eq!(player.next_input().map(|(sequence, _)| sequence), Some(6));
+}
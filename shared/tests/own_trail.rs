@@ -0,0 +1,47 @@
+use shared::models::{
+    game_settings::GameSettings, player_states::PlayerStates, power_up::EffectKind,
+};
+
+const TICKS: u32 = 150;
+
+// moves the player straight through the middle of the board - he must never hit his own trail
+fn survives_straight_line(effects: &[(EffectKind, u32)]) -> bool {
+    let settings = GameSettings::default();
+    let mut player = PlayerStates::new("player", "player");
+    player.position_x = -settings.board_width / 2.0 + settings.collision_radius;
+
+    for (kind, ticks) in effects {
+        player.add_effect(*kind, *ticks);
+    }
+
+    for _ in 0..TICKS {
+        player.move_player(&settings);
+        player.tick_effects();
+        player.collides_with_own_trail(&settings);
+        if !player.is_alive {
+            return false;
+        }
+    }
+    true
+}
+
+#[test]
+fn normal_player_survives_his_own_trail() {
+    assert!(survives_straight_line(&[]));
+}
+
+#[test]
+fn slowed_player_survives_his_own_trail() {
+    assert!(survives_straight_line(&[(EffectKind::Slowed, TICKS)]));
+}
+
+#[test]
+fn boosted_player_survives_his_own_trail() {
+    assert!(survives_straight_line(&[(EffectKind::SpeedBoost, TICKS)]));
+}
+
+// the newest points are close together when the slow effect ends
+#[test]
+fn player_survives_getting_faster_again() {
+    assert!(survives_straight_line(&[(EffectKind::Slowed, TICKS / 2)]));
+}
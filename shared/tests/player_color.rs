@@ -0,0 +1,34 @@
+use shared::models::player_color::PlayerColor;
+
+// pressing Change Color asks for the color after our own one
+#[test]
+fn taken_color_is_skipped_forward() {
+    let taken_colors = [PlayerColor::Cyan];
+    let color = PlayerColor::pick_free(Some(PlayerColor::Pink.next()), &taken_colors);
+    assert_eq!(color, PlayerColor::Yellow);
+}
+
+#[test]
+fn skipping_wraps_around_to_the_first_color() {
+    let taken_colors = [PlayerColor::Red, PlayerColor::Cyan];
+    let color = PlayerColor::pick_free(Some(PlayerColor::Red), &taken_colors);
+    assert_eq!(color, PlayerColor::Pink);
+}
+
+#[test]
+fn every_color_can_be_reached_by_cycling() {
+    let taken_colors = [PlayerColor::Cyan];
+    let mut color = PlayerColor::Pink;
+    let mut reached = vec![color];
+    for _ in 0..PlayerColor::ALL.len() {
+        color = PlayerColor::pick_free(Some(color.next()), &taken_colors);
+        reached.push(color);
+    }
+
+    for expected in PlayerColor::ALL
+        .iter()
+        .filter(|c| !taken_colors.contains(c))
+    {
+        assert!(reached.contains(expected));
+    }
+}
@@ -0,0 +1,56 @@
+use shared::models::{
+    game_settings::GameSettings, network_message::GameStateMessage, player_states::LobbyState,
+    replay::Replay, simulation::SimulationSync,
+};
+
+const FRAMES: u64 = 450;
+
+fn recorded_replay() -> Replay {
+    Replay {
+        lobby_id: "lobby".to_string(),
+        settings: GameSettings::default(),
+        recorded_at_millis: 1000,
+        frames: (0..FRAMES)
+            .map(|tick| GameStateMessage {
+                tick,
+                server_time_millis: 1000 + tick,
+                is_keyframe: tick == 0,
+                lobby_state: LobbyState::Running,
+                player_states: Vec::new(),
+                trail_updates: Vec::new(),
+                power_ups: Vec::new(),
+                round: 1,
+                rounds_per_match: 1,
+                scores: Vec::new(),
+                round_results: None,
+                simulation: SimulationSync::default(),
+            })
+            .collect(),
+    }
+}
+
+// the client gets the chunks one after another and has to end up with the whole replay
+#[test]
+fn chunks_are_put_back_together() {
+    let mut chunks = recorded_replay().chunks().into_iter();
+    let mut replay = chunks.next().unwrap().replay;
+
+    let mut is_complete = false;
+    for (index, chunk) in chunks.enumerate() {
+        assert!(!is_complete);
+        is_complete = replay.append_chunk(index as u32 + 1, chunk).unwrap();
+    }
+
+    assert!(is_complete);
+    let ticks: Vec<u64> = replay.frames.iter().map(|frame| frame.tick).collect();
+    assert_eq!(ticks, (0..FRAMES).collect::<Vec<u64>>());
+}
+
+#[test]
+fn missing_chunk_is_rejected() {
+    let chunks = recorded_replay().chunks();
+    assert!(chunks.len() > 2);
+
+    let mut replay = chunks[0].replay.clone();
+    assert!(replay.append_chunk(1, chunks[2].clone()).is_err());
+}
@@ -0,0 +1,108 @@
+use shared::models::{
+    game_match::GameMatch,
+    player_states::{LobbyState, PlayerStates},
+};
+
+const PLAYER_COUNT: usize = 4;
+
+// a match with four players that already counted down its first round
+fn running_match() -> GameMatch {
+    let mut game_match = GameMatch::new();
+    for index in 0..PLAYER_COUNT {
+        let id = format!("player-{}", index);
+        game_match
+            .simulation
+            .players
+            .push(PlayerStates::new(&id, &id));
+    }
+    game_match.start(7);
+    while game_match.lobby_state != LobbyState::Running {
+        game_match.advance(&[]);
+    }
+    game_match
+}
+
+// the players crash into the wall in the next tick
+fn crash(game_match: &mut GameMatch, player_ids: &[&str]) {
+    for player in game_match.simulation.players.iter_mut() {
+        if player_ids.contains(&player.id.as_str()) {
+            player.position_x = 10_000.0;
+        }
+    }
+    game_match.advance(&[]);
+}
+
+fn points(game_match: &GameMatch, player_id: &str) -> u32 {
+    game_match
+        .scores
+        .get(player_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+fn place(game_match: &GameMatch, player_id: &str) -> u32 {
+    game_match
+        .round_results()
+        .placements
+        .iter()
+        .find(|placement| placement.player_id == player_id)
+        .map(|placement| placement.place)
+        .unwrap()
+}
+
+#[test]
+fn players_dying_in_the_same_tick_share_their_place() {
+    let mut game_match = running_match();
+    crash(&mut game_match, &["player-0", "player-1"]);
+
+    assert_eq!(place(&game_match, "player-0"), 3);
+    assert_eq!(place(&game_match, "player-1"), 3);
+    assert_eq!(place(&game_match, "player-2"), 1);
+    assert_eq!(place(&game_match, "player-3"), 1);
+}
+
+// every survivor gets a point for each player that died - the ones that died get nothing for each other
+#[test]
+fn survivors_get_a_point_for_every_death() {
+    let mut game_match = running_match();
+    crash(&mut game_match, &["player-0", "player-1"]);
+
+    assert_eq!(points(&game_match, "player-0"), 0);
+    assert_eq!(points(&game_match, "player-1"), 0);
+    assert_eq!(points(&game_match, "player-2"), 2);
+    assert_eq!(points(&game_match, "player-3"), 2);
+    assert!(game_match.lobby_state == LobbyState::Running);
+}
+
+#[test]
+fn last_survivor_wins_the_round() {
+    let mut game_match = running_match();
+    crash(&mut game_match, &["player-0", "player-1"]);
+    crash(&mut game_match, &["player-2"]);
+
+    assert_eq!(points(&game_match, "player-2"), 2);
+    assert_eq!(points(&game_match, "player-3"), 3);
+    assert!(matches!(game_match.lobby_state, LobbyState::RoundOver(_)));
+
+    assert_eq!(place(&game_match, "player-3"), 1);
+    assert_eq!(place(&game_match, "player-2"), 2);
+    assert_eq!(place(&game_match, "player-0"), 3);
+    assert_eq!(place(&game_match, "player-1"), 3);
+    assert_eq!(game_match.scores()[0].player_id, "player-3");
+}
+
+// when the last players die together nobody survives - they all share the first place
+#[test]
+fn players_dying_together_at_the_end_share_the_first_place() {
+    let mut game_match = running_match();
+    crash(&mut game_match, &["player-0"]);
+    crash(&mut game_match, &["player-1", "player-2", "player-3"]);
+
+    assert!(matches!(game_match.lobby_state, LobbyState::RoundOver(_)));
+    assert_eq!(place(&game_match, "player-1"), 1);
+    assert_eq!(place(&game_match, "player-2"), 1);
+    assert_eq!(place(&game_match, "player-3"), 1);
+    assert_eq!(place(&game_match, "player-0"), 4);
+    assert_eq!(points(&game_match, "player-1"), 1);
+    assert_eq!(points(&game_match, "player-0"), 0);
+}
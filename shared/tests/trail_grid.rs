@@ -0,0 +1,75 @@
+use shared::models::{game_settings::GameSettings, trail_grid::TrailGrid};
+
+// with the default settings a cell is 20 wide and the board goes from -500 to 500
+fn grid_with(points: &[(f32, f32)]) -> TrailGrid {
+    let settings = GameSettings::default();
+    let mut grid = TrailGrid::default();
+    for (index, (x, y)) in points.iter().enumerate() {
+        grid.insert(index, *x, *y, &settings);
+    }
+    grid
+}
+
+fn nearby(grid: &TrailGrid, x: f32, y: f32) -> Vec<usize> {
+    let mut indices: Vec<usize> = grid.nearby(x, y).collect();
+    indices.sort();
+    indices
+}
+
+#[test]
+fn empty_grid_finds_nothing() {
+    let grid = TrailGrid::default();
+
+    assert!(nearby(&grid, 0.0, 0.0).is_empty());
+    assert!(nearby(&grid, -500.0, 500.0).is_empty());
+}
+
+#[test]
+fn points_in_the_corners_are_found() {
+    let grid = grid_with(&[
+        (-500.0, -500.0),
+        (500.0, 500.0),
+        (-500.0, 500.0),
+        (500.0, -500.0),
+    ]);
+
+    assert_eq!(nearby(&grid, -500.0, -500.0), vec![0]);
+    assert_eq!(nearby(&grid, 500.0, 500.0), vec![1]);
+    assert_eq!(nearby(&grid, -490.0, 490.0), vec![2]);
+    assert_eq!(nearby(&grid, 490.0, -490.0), vec![3]);
+}
+
+// positions outside of the board end up in the cells at the edge
+#[test]
+fn points_outside_of_the_board_are_found_at_the_edge() {
+    let grid = grid_with(&[(-600.0, 0.0), (0.0, 700.0)]);
+
+    assert_eq!(nearby(&grid, -500.0, 0.0), vec![0]);
+    assert_eq!(nearby(&grid, -550.0, 10.0), vec![0]);
+    assert_eq!(nearby(&grid, 0.0, 500.0), vec![1]);
+    assert!(nearby(&grid, 0.0, 0.0).is_empty());
+}
+
+// the point lies in the cell from 0 to 20 - only the cells from -20 to 40 are around it
+#[test]
+fn only_the_neighbouring_cells_are_searched() {
+    let grid = grid_with(&[(0.0, 0.0)]);
+
+    assert_eq!(nearby(&grid, -20.0, 0.0), vec![0]);
+    assert_eq!(nearby(&grid, 39.99, 0.0), vec![0]);
+    assert_eq!(nearby(&grid, 0.0, -20.0), vec![0]);
+    assert_eq!(nearby(&grid, 0.0, 39.99), vec![0]);
+
+    assert!(nearby(&grid, -20.01, 0.0).is_empty());
+    assert!(nearby(&grid, 40.0, 0.0).is_empty());
+    assert!(nearby(&grid, 0.0, -20.01).is_empty());
+    assert!(nearby(&grid, 0.0, 40.0).is_empty());
+}
+
+#[test]
+fn removed_point_is_not_found_anymore() {
+    let mut grid = grid_with(&[(0.0, 0.0), (5.0, 5.0)]);
+    grid.remove_newest(5.0, 5.0);
+
+    assert_eq!(nearby(&grid, 0.0, 0.0), vec![0]);
+}
@@ -0,0 +1,74 @@
+use shared::models::{
+    network_message::{JoinLobbyMessage, NetworkMessage},
+    wire_format::{resume_token_from_query, EncodedMessage, WireFormat},
+};
+
+// the backend gets the query of the url without the leading question mark
+fn parsed(query: &str) -> (WireFormat, Option<String>) {
+    let query = query.strip_prefix('?');
+    (
+        WireFormat::from_query(query),
+        resume_token_from_query(query),
+    )
+}
+
+#[test]
+fn query_round_trip() {
+    for wire_format in [WireFormat::Json, WireFormat::MessagePack] {
+        assert_eq!(parsed(&wire_format.to_query(None)), (wire_format, None));
+        assert_eq!(
+            parsed(&wire_format.to_query(Some("token"))),
+            (wire_format, Some("token".to_string()))
+        );
+    }
+}
+
+#[test]
+fn json_needs_no_query() {
+    assert_eq!(WireFormat::Json.to_query(None), "");
+}
+
+#[test]
+fn missing_or_unknown_format_falls_back_to_json() {
+    assert_eq!(WireFormat::from_query(None), WireFormat::Json);
+    assert_eq!(WireFormat::from_query(Some("")), WireFormat::Json);
+    assert_eq!(WireFormat::from_query(Some("format=xml")), WireFormat::Json);
+    assert_eq!(WireFormat::from_query(Some("format")), WireFormat::Json);
+    assert_eq!(
+        WireFormat::from_query(Some("resume=token")),
+        WireFormat::Json
+    );
+    assert_eq!(
+        WireFormat::from_query(Some("resume=token&format=msgpack")),
+        WireFormat::MessagePack
+    );
+}
+
+#[test]
+fn message_round_trip() {
+    for wire_format in [WireFormat::Json, WireFormat::MessagePack] {
+        let message = NetworkMessage::JoinLobby(JoinLobbyMessage {
+            lobby_id: "lobby".to_string(),
+            as_spectator: true,
+        });
+
+        let decoded = match message.encode(wire_format).unwrap() {
+            EncodedMessage::Text(text) => {
+                assert_eq!(wire_format, WireFormat::Json);
+                NetworkMessage::decode_text(&text)
+            }
+            EncodedMessage::Binary(bytes) => {
+                assert_eq!(wire_format, WireFormat::MessagePack);
+                NetworkMessage::decode_binary(&bytes)
+            }
+        };
+
+        match decoded {
+            Ok(NetworkMessage::JoinLobby(join_lobby)) => {
+                assert_eq!(join_lobby.lobby_id, "lobby");
+                assert!(join_lobby.as_spectator);
+            }
+            _ => panic!("message changed on its way"),
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use std::{env, process, str::FromStr, time::Instant};
+
+use shared::models::{
+    bot::Bot,
+    display_name::unique_display_name,
+    game_match::GameMatch,
+    game_settings::GameSettings,
+    network_message::{BotDifficulty, PlayerScore, RoundResults},
+    player_states::{DeathCause, LobbyState, PlayerStates},
+    simulation::{PlayerInput, SimulationState},
+    simulation_rng::SimulationRng,
+};
+
+// bots that never crash would keep a round running forever - their match is stopped after this long
+const MAX_ROUND_SECONDS: f32 = 300.0;
+
+const USAGE: &str =
+    "Usage: simulator [--matches <count>] [--easy <bots>] [--hard <bots>] [--seed <seed>]
+                 [--board-width <width>] [--board-height <height>] [--move-speed <speed>]
+                 [--rotation-speed <speed>] [--tick-rate <ticks>] [--collision-radius <radius>]";
+
+/// What the simulator runs - every match gets its own seed, counted up from the given one
+struct Options {
+    matches: u64,
+    easy_bots: usize,
+    hard_bots: usize,
+    seed: u64,
+    settings: GameSettings,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            matches: 1000,
+            easy_bots: 2,
+            hard_bots: 2,
+            seed: 0,
+            settings: GameSettings::default(),
+        };
+
+        while let Some(flag) = args.next() {
+            if flag == "--help" {
+                return Err(USAGE.to_string());
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} needs a value\n{}", flag, USAGE))?;
+            match flag.as_str() {
+                "--matches" => options.matches = parse_value(&flag, &value)?,
+                "--easy" => options.easy_bots = parse_value(&flag, &value)?,
+                "--hard" => options.hard_bots = parse_value(&flag, &value)?,
+                "--seed" => options.seed = parse_value(&flag, &value)?,
+                "--board-width" => options.settings.board_width = parse_value(&flag, &value)?,
+                "--board-height" => options.settings.board_height = parse_value(&flag, &value)?,
+                "--move-speed" => options.settings.move_speed = parse_value(&flag, &value)?,
+                "--rotation-speed" => options.settings.rotation_speed = parse_value(&flag, &value)?,
+                "--tick-rate" => options.settings.ticks_per_second = parse_value(&flag, &value)?,
+                "--collision-radius" => {
+                    options.settings.collision_radius = parse_value(&flag, &value)?
+                }
+                _ => return Err(format!("Unknown flag {}\n{}", flag, USAGE)),
+            }
+        }
+
+        if options.easy_bots + options.hard_bots < 2 {
+            return Err("A match needs at least two bots".to_string());
+        }
+        options.settings.validate()?;
+        Ok(options)
+    }
+
+    fn difficulties(&self) -> Vec<BotDifficulty> {
+        let mut difficulties = vec![BotDifficulty::Easy; self.easy_bots];
+        difficulties.extend(vec![BotDifficulty::Hard; self.hard_bots]);
+        difficulties
+    }
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
+
+/// Everything that happened to a single bot over all matches
+struct BotStatistics {
+    name: String,
+    matches_won: u32,
+    rounds_won: u32,
+    points: u32,
+    kills: u32, // other bots that crashed into his trail
+    wall_deaths: u32,
+    own_trail_deaths: u32,
+    player_trail_deaths: u32,
+}
+
+#[derive(Default)]
+struct Statistics {
+    bots: Vec<BotStatistics>,
+    matches: u32,
+    drawn_matches: u32, // the best score was shared - nobody won
+    stopped_matches: u32,
+    rounds: u32,
+    rounds_without_survivor: u32,
+    running_ticks: u64,
+}
+
+impl Statistics {
+    fn new(difficulties: &[BotDifficulty]) -> Self {
+        let mut names: Vec<String> = Vec::new();
+        for difficulty in difficulties {
+            let name = Bot::new(*difficulty).name();
+            let taken_names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+            names.push(unique_display_name(name, &taken_names));
+        }
+
+        Statistics {
+            bots: names
+                .into_iter()
+                .map(|name| BotStatistics {
+                    name,
+                    matches_won: 0,
+                    rounds_won: 0,
+                    points: 0,
+                    kills: 0,
+                    wall_deaths: 0,
+                    own_trail_deaths: 0,
+                    player_trail_deaths: 0,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    // the bots are identified by their name - it is unique
+    fn bot(&mut self, name: &str) -> Option<&mut BotStatistics> {
+        self.bots.iter_mut().find(|bot| bot.name == name)
+    }
+
+    fn add_round(&mut self, round_results: &RoundResults, running_ticks: u32) {
+        self.rounds += 1;
+        self.running_ticks += running_ticks as u64;
+
+        let has_survivor = round_results.placements.iter().any(|p| p.death.is_none());
+        if !has_survivor {
+            self.rounds_without_survivor += 1;
+        }
+
+        for placement in round_results.placements.iter() {
+            let Some(death) = &placement.death else {
+                if let Some(bot) = self.bot(&placement.player_id) {
+                    bot.rounds_won += 1;
+                }
+                continue;
+            };
+
+            // the trail a bot crashed into counts as a kill for its owner
+            if let DeathCause::PlayerTrail(killer_id) = &death.cause {
+                if let Some(killer) = self.bot(killer_id) {
+                    killer.kills += 1;
+                }
+            }
+
+            if let Some(bot) = self.bot(&placement.player_id) {
+                match death.cause {
+                    DeathCause::Wall => bot.wall_deaths += 1,
+                    DeathCause::OwnTrail => bot.own_trail_deaths += 1,
+                    DeathCause::PlayerTrail(_) => bot.player_trail_deaths += 1,
+                }
+            }
+        }
+    }
+
+    fn add_match(&mut self, scores: &[PlayerScore]) {
+        self.matches += 1;
+        for score in scores {
+            if let Some(bot) = self.bot(&score.player_id) {
+                bot.points += score.points;
+            }
+        }
+
+        // the scores are sorted - the best player comes first
+        match scores {
+            [first, second, ..] if first.points == second.points => self.drawn_matches += 1,
+            [first, ..] => {
+                if let Some(bot) = self.bot(&first.player_id) {
+                    bot.matches_won += 1;
+                }
+            }
+            [] => {}
+        }
+    }
+
+    fn print(&self, options: &Options, seconds: f64) {
+        println!(
+            "Simulated {} matches ({} rounds) in {:.1}s - seeds {} to {}",
+            self.matches,
+            self.rounds,
+            seconds,
+            options.seed,
+            options.seed.wrapping_add(options.matches.saturating_sub(1))
+        );
+        let settings = &options.settings;
+        println!(
+            "Board {}x{}, move speed {}, rotation speed {}, tick rate {}, collision radius {}",
+            settings.board_width,
+            settings.board_height,
+            settings.move_speed,
+            settings.rotation_speed,
+            settings.ticks_per_second,
+            settings.collision_radius
+        );
+        println!();
+
+        println!(
+            "{:<14} {:>12} {:>11} {:>13} {:>7} {:>11} {:>11} {:>13}",
+            "Bot",
+            "Matches won",
+            "Rounds won",
+            "Points/match",
+            "Kills",
+            "Wall",
+            "Own trail",
+            "Other trail"
+        );
+        for bot in self.bots.iter() {
+            let deaths = bot.wall_deaths + bot.own_trail_deaths + bot.player_trail_deaths;
+            println!(
+                "{:<14} {:>11.1}% {:>10.1}% {:>13.1} {:>7} {:>10.1}% {:>10.1}% {:>12.1}%",
+                bot.name,
+                percentage(bot.matches_won, self.matches),
+                percentage(bot.rounds_won, self.rounds),
+                bot.points as f64 / self.matches.max(1) as f64,
+                bot.kills,
+                percentage(bot.wall_deaths, deaths),
+                percentage(bot.own_trail_deaths, deaths),
+                percentage(bot.player_trail_deaths, deaths),
+            );
+        }
+        println!();
+
+        println!(
+            "Drawn matches: {:.1}%",
+            percentage(self.drawn_matches, self.matches)
+        );
+        println!(
+            "Rounds without survivor: {:.1}%",
+            percentage(self.rounds_without_survivor, self.rounds)
+        );
+        println!(
+            "Average round length: {:.1}s",
+            self.running_ticks as f64
+                / self.rounds.max(1) as f64
+                / settings.ticks_per_second as f64
+        );
+        if self.stopped_matches > 0 {
+            println!(
+                "Stopped matches (a round took longer than {}s): {}",
+                MAX_ROUND_SECONDS, self.stopped_matches
+            );
+        }
+    }
+}
+
+fn percentage(count: u32, total: u32) -> f64 {
+    count as f64 * 100.0 / total.max(1) as f64
+}
+
+/// Plays a whole match without any network - the same seed always gives the same match
+fn play_match(
+    difficulties: &[BotDifficulty],
+    settings: &GameSettings,
+    seed: u64,
+    statistics: &mut Statistics,
+) {
+    let mut game_match = GameMatch::new();
+    game_match.simulation.settings = *settings;
+    let max_round_ticks = settings.ticks(MAX_ROUND_SECONDS);
+    let mut bots: Vec<Bot> = Vec::new();
+    for (difficulty, bot_statistics) in difficulties.iter().zip(statistics.bots.iter()) {
+        let name = &bot_statistics.name;
+        game_match
+            .simulation
+            .players
+            .push(PlayerStates::new(name, name));
+        bots.push(Bot::new(*difficulty));
+    }
+
+    // the bots get their own random numbers, like on the server
+    let mut bot_rng = SimulationRng::new(seed);
+    game_match.start(seed);
+
+    let mut running_ticks = 0;
+    while game_match.is_running() {
+        let was_running = game_match.lobby_state == LobbyState::Running;
+        let inputs = if was_running {
+            bot_inputs(&mut bots, &game_match.simulation, &mut bot_rng)
+        } else {
+            Vec::new()
+        };
+
+        game_match.advance(&inputs);
+
+        if !was_running {
+            continue;
+        }
+        running_ticks += 1;
+
+        if game_match.lobby_state != LobbyState::Running {
+            statistics.add_round(&game_match.round_results(), running_ticks);
+            running_ticks = 0;
+        } else if running_ticks >= max_round_ticks {
+            statistics.stopped_matches += 1;
+            return;
+        }
+    }
+
+    statistics.add_match(&game_match.scores());
+}
+
+// the bots steer every tick - their inputs are applied right away
+fn bot_inputs(
+    bots: &mut [Bot],
+    simulation: &SimulationState,
+    rng: &mut SimulationRng,
+) -> Vec<PlayerInput> {
+    let players = &simulation.players;
+    bots.iter_mut()
+        .zip(players)
+        .filter(|(_, player)| player.is_alive)
+        .map(|(bot, player)| {
+            let (sequence, direction) = bot.next_input(player, players, &simulation.settings, rng);
+            PlayerInput {
+                player_id: player.id.clone(),
+                sequence,
+                direction,
+            }
+        })
+        .collect()
+}
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    let difficulties = options.difficulties();
+    let mut statistics = Statistics::new(&difficulties);
+    let started_at = Instant::now();
+
+    for index in 0..options.matches {
+        play_match(
+            &difficulties,
+            &options.settings,
+            options.seed.wrapping_add(index),
+            &mut statistics,
+        );
+    }
+
+    statistics.print(&options, started_at.elapsed().as_secs_f64());
+}